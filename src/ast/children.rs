@@ -0,0 +1,78 @@
+use crate::ast::{Ast, ExprId, ExprKind, NodeId, StmtId, StmtKind};
+
+impl Ast {
+    /// Returns the direct children of `node` in source order.
+    pub fn children(&self, node: NodeId) -> Vec<NodeId> {
+        match node {
+            NodeId::Expr(expr_id) => self.expr_children(expr_id),
+            NodeId::Stmt(stmt_id) => self.stmt_children(stmt_id),
+        }
+    }
+
+    /// Returns the direct children of an expression in source order.
+    ///
+    /// The children of a block are its statements, and the only child of a `func` expression is its
+    /// body; parameters and type annotations are not nodes.
+    pub fn expr_children(&self, expr_id: ExprId) -> Vec<NodeId> {
+        let children = match &self.query_expr(expr_id).kind {
+            ExprKind::Block(block) => return block.stmts.iter().map(|stmt| NodeId::Stmt(*stmt)).collect(),
+            ExprKind::Func(func) => func.decl.body.into_iter().collect(),
+            ExprKind::Binary(binary) => vec![binary.left, binary.right],
+            ExprKind::Unary(unary) => vec![unary.operand],
+            ExprKind::Parenthesized(parenthesized) => vec![parenthesized.expression],
+            ExprKind::Assignment(assignment) => vec![assignment.expression],
+            ExprKind::Discard(discard) => vec![discard.expression],
+            ExprKind::Call(call) => {
+                let mut children = vec![call.callee];
+                children.extend(&call.arguments);
+                children
+            }
+            ExprKind::If(if_expr) => {
+                let mut children = vec![if_expr.condition, if_expr.then_branch];
+                children.extend(if_expr.else_branch.as_ref().map(|else_branch| else_branch.expr));
+                children
+            }
+            ExprKind::Number(_) | ExprKind::Variable(_) | ExprKind::Boolean(_) | ExprKind::Rec(_) | ExprKind::Error(_) => vec![],
+        };
+        children.into_iter().map(NodeId::Expr).collect()
+    }
+
+    /// Returns the direct children of a statement in source order.
+    pub fn stmt_children(&self, stmt_id: StmtId) -> Vec<NodeId> {
+        let children = match &self.query_stmt(stmt_id).kind {
+            StmtKind::Expr(expr_id) => vec![*expr_id],
+            StmtKind::Let(let_stmt) => vec![let_stmt.initializer],
+            StmtKind::While(while_stmt) => vec![while_stmt.condition, while_stmt.body],
+            StmtKind::Return(return_stmt) => return_stmt.return_value.into_iter().collect(),
+            StmtKind::Extern(_) | StmtKind::Break(_) | StmtKind::Continue(_) => vec![],
+        };
+        children.into_iter().map(NodeId::Expr).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::printer::test::parse;
+    use crate::ast::{ExprKind, NodeId};
+
+    #[test]
+    pub fn should_list_children_in_source_order() {
+        let ast = parse("if c { f(1, 2) } else { 3 }");
+        let (if_id, condition, then_branch, else_branch) = ast.expressions.iter().find_map(|expr| match &expr.kind {
+            ExprKind::If(if_expr) => Some((expr.id, if_expr.condition, if_expr.then_branch, if_expr.else_branch.as_ref()?.expr)),
+            _ => None,
+        }).expect("Expected an if expression with an else branch");
+        let (call_id, call_children) = ast.expressions.iter().find_map(|expr| match &expr.kind {
+            ExprKind::Call(call) => Some((expr.id, vec![call.callee, call.arguments[0], call.arguments[1]])),
+            _ => None,
+        }).expect("Expected a call expression");
+        let block_children = match &ast.query_expr(then_branch).kind {
+            ExprKind::Block(block) => block.stmts.iter().map(|stmt| NodeId::Stmt(*stmt)).collect::<Vec<_>>(),
+            _ => panic!("Expected a block"),
+        };
+
+        assert_eq!(ast.expr_children(if_id), vec![NodeId::Expr(condition), NodeId::Expr(then_branch), NodeId::Expr(else_branch)]);
+        assert_eq!(ast.expr_children(then_branch), block_children);
+        assert_eq!(ast.children(NodeId::Expr(call_id)), call_children.into_iter().map(NodeId::Expr).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use rs_compiler::Idx;
+
+use crate::ast::lexer::Token;
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{AssignExpr, Ast, BoolExpr, Expr, ExprId, ExternStmt, FuncExpr, LetStmt, NumberExpr, RecExpr, Stmt, UnaryExpr, VarExpr};
+use crate::compilation_unit::VariableIdx;
+use crate::text::span::TextSpan;
+
+/// Where a variable is written and read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DefUse {
+    /// The declaring identifier followed by the identifiers of assignments.
+    pub defs: Vec<TextSpan>,
+    /// Every read of the variable.
+    pub uses: Vec<TextSpan>,
+}
+
+/// Collects the definitions and uses of every variable of a resolved `ast`.
+///
+/// Variables are told apart by the resolver, so a shadowing declaration starts its own chain.
+/// Only nodes reachable from the items are visited, so nodes a pass replaced are not counted.
+/// Spans are listed in source order.
+pub fn def_use_chains(ast: &mut Ast) -> HashMap<VariableIdx, DefUse> {
+    let mut collector = DefUseCollector { chains: HashMap::new() };
+    ast.visit(&mut collector);
+    let mut chains = collector.chains;
+    for chain in chains.values_mut() {
+        chain.defs.sort_by_key(|span| span.start);
+        chain.uses.sort_by_key(|span| span.start);
+    }
+    chains
+}
+
+struct DefUseCollector {
+    chains: HashMap<VariableIdx, DefUse>,
+}
+
+impl DefUseCollector {
+    fn add_def(&mut self, variable_idx: VariableIdx, identifier: &Token) {
+        self.chains.entry(variable_idx).or_default().defs.push(span_of(identifier));
+    }
+
+    fn add_use(&mut self, variable_idx: VariableIdx, identifier: &Token) {
+        self.chains.entry(variable_idx).or_default().uses.push(span_of(identifier));
+    }
+}
+
+fn span_of(token: &Token) -> TextSpan {
+    TextSpan::new(token.span.start, token.span.end, token.span.literal.clone())
+}
+
+impl ASTVisitor for DefUseCollector {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        for parameter in &func_expr.decl.parameters {
+            if let Some(variable_idx) = parameter.variable_idx {
+                self.add_def(variable_idx, &parameter.identifier);
+            }
+        }
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {
+        self.add_def(extern_statement.variable_idx, &extern_statement.identifier);
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.add_def(let_statement.variable_idx, &let_statement.identifier);
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, expr: &Expr) {
+        self.add_def(assign_expr.variable_idx, &assign_expr.identifier);
+        self.visit_expression(ast, assign_expr.expression);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {
+        self.add_use(variable_expression.variable_idx, &variable_expression.identifier);
+    }
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+}
+
+/// Renders `chains` one variable per line, as `name: defs <offsets>; uses <offsets>`.
+///
+/// Variables are ordered by their index, so the output is the same on every run even though the
+/// map's iteration order is not.
+pub fn render_def_use_chains(chains: &HashMap<VariableIdx, DefUse>) -> String {
+    let mut chains: Vec<(&VariableIdx, &DefUse)> = chains.iter().collect();
+    chains.sort_by_key(|(variable_idx, _)| variable_idx.as_index());
+    chains.iter().map(|(_, chain)| {
+        let name = chain.defs.first().or(chain.uses.first()).map_or("", |span| span.literal.as_str());
+        format!("{}: defs {}; uses {}\n", name, render_offsets(&chain.defs), render_offsets(&chain.uses))
+    }).collect()
+}
+
+fn render_offsets(spans: &[TextSpan]) -> String {
+    spans.iter().map(|span| span.start.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::def_use::{def_use_chains, render_def_use_chains};
+    use crate::ast::StmtKind;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::passes::double_negation::DoubleNegationEliminator;
+    use crate::text::span::TextSpan;
+
+    fn starts(spans: &[TextSpan]) -> Vec<usize> {
+        spans.iter().map(|span| span.start).collect()
+    }
+
+    #[test]
+    pub fn should_collect_declaration_assignment_and_reads() {
+        let input = "\
+        let x = 1
+        x = 2
+        let y = x + x
+        ";
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let ast = &mut compilation_unit.ast;
+        let x = ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) if let_stmt.identifier.span.literal == "x" => Some(let_stmt.variable_idx),
+            _ => None,
+        }).expect("Expected a declaration of x");
+
+        let chains = def_use_chains(ast);
+
+        let declaration = input.find("let x").unwrap() + 4;
+        let assignment = input.find("x = 2").unwrap();
+        let first_read = input.find("x + x").unwrap();
+        assert_eq!(starts(&chains[&x].defs), vec![declaration, assignment]);
+        assert_eq!(starts(&chains[&x].uses), vec![first_read, first_read + 4]);
+    }
+
+    #[test]
+    pub fn should_render_chains_in_the_same_order_on_every_run() {
+        let input = "let a = 1\nlet b = a\nlet c = b + a\nb = c";
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+
+        let first = render_def_use_chains(&def_use_chains(&mut compilation_unit.ast));
+        let second = render_def_use_chains(&def_use_chains(&mut compilation_unit.ast));
+
+        assert_eq!(first, second);
+        assert_eq!(first, "a: defs 4; uses 18, 32\nb: defs 14, 34; uses 28\nc: defs 24; uses 38\n");
+    }
+
+    #[test]
+    pub fn should_not_count_nodes_replaced_by_a_pass() {
+        let input = "let x = 1\nlet y = --x";
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        DoubleNegationEliminator::new().eliminate(&mut compilation_unit.ast);
+
+        let chains = render_def_use_chains(&def_use_chains(&mut compilation_unit.ast));
+
+        assert_eq!(chains, "x: defs 4; uses 20\ny: defs 14; uses \n");
+    }
+}
@@ -0,0 +1,64 @@
+use crate::ast::{Ast, ExprKind, ItemKind, NodeId};
+use crate::compilation_unit::FunctionIdx;
+use crate::typings::Type;
+
+impl Ast {
+    /// Returns the innermost function whose body contains `node`, or `None` for a top-level node.
+    ///
+    /// A `func` expression itself belongs to the function it is written in. Functions are read
+    /// from the types of `func` expressions, so this is meant to run after resolution.
+    pub fn enclosing_function(&self, node: NodeId) -> Option<FunctionIdx> {
+        self.items.iter()
+            .find_map(|item| match &item.kind {
+                ItemKind::Stmt(stmt_id) => self.find_enclosing_function(NodeId::Stmt(*stmt_id), node, None),
+            })
+            .flatten()
+    }
+
+    /// Returns `Some` with the function enclosing `node` if `node` is `current` or inside it.
+    fn find_enclosing_function(&self, current: NodeId, node: NodeId, function: Option<FunctionIdx>) -> Option<Option<FunctionIdx>> {
+        if node == current {
+            return Some(function);
+        }
+        let function = match current {
+            NodeId::Expr(expr_id) => {
+                let expr = self.query_expr(expr_id);
+                match (&expr.kind, &expr.ty) {
+                    (ExprKind::Func(_), Type::Function(function_idx)) => Some(*function_idx),
+                    _ => function,
+                }
+            }
+            NodeId::Stmt(_) => function,
+        };
+        self.children(current).into_iter().find_map(|child| self.find_enclosing_function(child, node, function))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rs_compiler::Idx;
+
+    use crate::ast::{NodeId, StmtKind};
+    use crate::compilation_unit::{CompilationUnit, FunctionIdx};
+
+    #[test]
+    pub fn should_map_statement_to_its_function_and_top_level_statement_to_none() {
+        let input = "\
+        let f = func -> int {
+            return 1
+        }
+        let a = 2
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let ast = &compilation_unit.ast;
+        let find_stmt = |matches: fn(&StmtKind) -> bool| ast.statements.iter()
+            .find(|stmt| matches(&stmt.kind))
+            .map(|stmt| NodeId::Stmt(stmt.id))
+            .expect("Expected statement");
+        let return_stmt = find_stmt(|kind| matches!(kind, StmtKind::Return(_)));
+        let top_level_let = find_stmt(|kind| matches!(kind, StmtKind::Let(let_stmt) if let_stmt.identifier.span.literal == "a"));
+
+        assert_eq!(ast.enclosing_function(return_stmt), Some(FunctionIdx::new(0)));
+        assert_eq!(ast.enclosing_function(top_level_let), None);
+    }
+}
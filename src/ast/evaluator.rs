@@ -1,9 +1,9 @@
 use std::collections::HashMap;
-use rs_compiler::Idx;
+use std::fmt::{Display, Formatter};
 
-use crate::ast::{AssignExpr, Ast, BinaryExpr, BinOpKind, BlockExpr, BoolExpr, CallExpr, Expr, ExprId, FuncExpr, FunctionDeclaration, IfExpr, LetStmt, NumberExpr, ParenthesizedExpr, RecExpr, Stmt, UnaryExpr, UnOpKid, VarExpr, WhileStmt};
+use crate::ast::{AssignExpr, Ast, BinaryExpr, BinOpKind, BlockExpr, BoolExpr, BreakStmt, CallExpr, ContinueStmt, DiscardExpr, Expr, ExprId, ExternStmt, FuncExpr, FunctionDeclaration, IfExpr, LetStmt, NumberExpr, ParenthesizedExpr, RecExpr, Stmt, StmtId, UnaryExpr, UnOpKid, VarExpr, WhileStmt};
 use crate::ast::visitor::ASTVisitor;
-use crate::compilation_unit::{FunctionIdx, GlobalScope, VariableIdx};
+use crate::compilation_unit::{Function, FunctionIdx, GlobalScope, VariableIdx};
 use crate::text::span::TextSpan;
 use crate::typings::Type;
 #[derive(Debug)]
@@ -98,20 +98,119 @@ impl Value {
             _ => panic!("Expected function value")
         }
     }
+
+    pub fn is_of_type(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (Value::Number(_), Type::Int) => true,
+            // there are no float values yet, so a float is an int the type checker widened
+            (Value::Number(_), Type::Float) => true,
+            (Value::Boolean(_), Type::Bool) => true,
+            (Value::Function(_), Type::Function(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
 }
 
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Runtime error: {}", self.message)
+    }
+}
+
+/// Rust implementation of an `extern` function, registered with [`ASTEvaluator::register_host_fn`].
+pub type HostFunction = Box<dyn Fn(&[Value]) -> Result<Value, RuntimeError>>;
+
+
+/// Set by `break`/`continue` and consumed by the innermost enclosing loop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LoopControl {
+    Break,
+    Continue,
+}
 
 pub struct ASTEvaluator<'a> {
     pub last_value: Option<Value>,
     pub frames: Frames,
     pub global_scope: &'a GlobalScope,
+    host_functions: HashMap<String, HostFunction>,
+    loop_control: Option<LoopControl>,
+    /// Set by the first runtime error, after which nothing else is evaluated.
+    runtime_error: Option<RuntimeError>,
 }
 
 impl<'a> ASTEvaluator<'a> {
     pub fn new(
         global_scope: &'a GlobalScope,
     ) -> Self {
-        Self { last_value: None, frames: Frames::new(), global_scope }
+        Self { last_value: None, frames: Frames::new(), global_scope, host_functions: HashMap::new(), loop_control: None, runtime_error: None }
+    }
+
+    /// Registers the implementation of the extern function `name`.
+    pub fn register_host_fn(&mut self, name: &str, function: HostFunction) {
+        self.host_functions.insert(name.to_string(), function);
+    }
+
+    /// Sets the value of the predeclared global `name` before the program runs.
+    pub fn set_global(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        let variable_idx = self.global_scope.lookup_predeclared_global(name)
+            .ok_or_else(|| RuntimeError::new(format!("No predeclared global '{}'", name)))?;
+        let variable = self.global_scope.variables.get(variable_idx);
+        if !value.is_of_type(&variable.ty) {
+            return Err(RuntimeError::new(format!("Predeclared global '{}' is of type '{}'", name, variable.ty)));
+        }
+        self.frames.insert(variable_idx, value);
+        Ok(())
+    }
+
+    /// Evaluates all items of `ast`, returning the value of the last one.
+    pub fn evaluate(&mut self, ast: &mut Ast) -> Result<Option<Value>, RuntimeError> {
+        ast.visit(self);
+        self.result()
+    }
+
+    /// Evaluates the single expression `expr`, such as the body of the entry point.
+    pub fn evaluate_expression(&mut self, ast: &mut Ast, expr: ExprId) -> Result<Option<Value>, RuntimeError> {
+        self.visit_expression(ast, expr);
+        self.result()
+    }
+
+    fn result(&mut self) -> Result<Option<Value>, RuntimeError> {
+        match self.runtime_error.take() {
+            Some(error) => Err(error),
+            None => Ok(self.last_value),
+        }
+    }
+
+    fn has_failed(&self) -> bool {
+        self.runtime_error.is_some()
+    }
+
+    fn call_host_function(&self, function: &Function, arguments: &[Value]) -> Result<Value, RuntimeError> {
+        let name = function.name.as_deref().unwrap_or("<anonymous>");
+        let host_function = self.host_functions
+            .get(name)
+            .ok_or_else(|| RuntimeError::new(format!("No host implementation for extern function '{}'", name)))?;
+        if arguments.len() != function.parameters.len() {
+            return Err(RuntimeError::new(format!("Host function '{}' expects {} arguments, but was given {}", name, function.parameters.len(), arguments.len())));
+        }
+        for (argument, parameter) in arguments.iter().zip(function.parameters.iter()) {
+            let parameter = self.global_scope.variables.get(*parameter);
+            if !argument.is_of_type(&parameter.ty) {
+                return Err(RuntimeError::new(format!("Host function '{}' expects argument '{}' of type '{}'", name, parameter.name, parameter.ty)));
+            }
+        }
+        host_function(arguments)
     }
 
     fn push_frame(&mut self) {
@@ -128,13 +227,40 @@ impl<'a> ASTEvaluator<'a> {
 }
 
 impl<'a> ASTVisitor for ASTEvaluator<'a> {
-    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {}
+    fn visit_statement(&mut self, ast: &mut Ast, statement: StmtId) {
+        if self.has_failed() {
+            return;
+        }
+        self.do_visit_statement(ast, statement);
+    }
+
+    fn visit_expression(&mut self, ast: &mut Ast, expression: ExprId) {
+        if self.has_failed() {
+            return;
+        }
+        self.do_visit_expression(ast, expression);
+    }
+
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        let function = match ast.query_expr(expr_id).ty {
+            Type::Function(function) => function,
+            _ => panic!("Expected function type")
+        };
+        self.last_value = Some(Value::Function(function));
+    }
+
+    fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {
+        self.frames.insert(extern_statement.variable_idx, Value::Function(extern_statement.function_idx));
+    }
 
     fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
         self.push_frame();
         self.visit_expression(ast, while_statement.condition);
-        while self.expect_last_value().expect_boolean() {
+        while !self.has_failed() && self.expect_last_value().expect_boolean() {
             self.visit_expression(ast, while_statement.body);
+            if let Some(LoopControl::Break) = self.loop_control.take() {
+                break;
+            }
             self.visit_expression(ast, while_statement.condition);
         }
         self.pop_frame();
@@ -144,13 +270,28 @@ impl<'a> ASTVisitor for ASTEvaluator<'a> {
         self.push_frame();
         for statement in &block_statement.stmts {
             self.visit_statement(ast, *statement);
+            if self.loop_control.is_some() {
+                break;
+            }
         }
         self.pop_frame();
     }
 
+    fn visit_break_statement(&mut self, ast: &mut Ast, break_statement: &BreakStmt) {
+        self.loop_control = Some(LoopControl::Break);
+    }
+
+    fn visit_continue_statement(&mut self, ast: &mut Ast, continue_statement: &ContinueStmt) {
+        self.loop_control = Some(LoopControl::Continue);
+    }
+
     fn visit_if_expression(&mut self, ast: &mut Ast, if_statement: &IfExpr, expr: &Expr) {
         self.push_frame();
         self.visit_expression(ast, if_statement.condition);
+        if self.has_failed() {
+            self.pop_frame();
+            return;
+        }
         if self.expect_last_value().expect_boolean() {
             self.push_frame();
             self.visit_expression(ast, if_statement.then_branch);
@@ -167,6 +308,9 @@ impl<'a> ASTVisitor for ASTEvaluator<'a> {
 
     fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
         self.visit_expression(ast, let_statement.initializer);
+        if self.has_failed() {
+            return;
+        }
         self.frames.insert(let_statement.variable_idx, self.expect_last_value());
     }
 
@@ -189,25 +333,56 @@ impl<'a> ASTVisitor for ASTEvaluator<'a> {
         let mut arguments = Vec::new();
         for argument in &call_expression.arguments {
             self.visit_expression(ast, *argument);
+            if self.has_failed() {
+                return;
+            }
             arguments.push(self.last_value.unwrap());
         }
+        let body = match function.body {
+            Some(body) => body,
+            None => {
+                match self.call_host_function(function, &arguments) {
+                    Ok(value) => self.last_value = Some(value),
+                    Err(error) => self.runtime_error = Some(error),
+                }
+                return;
+            }
+        };
         self.push_frame();
         for (argument, param) in arguments.iter().zip(function.parameters.iter()) {
             self.frames.insert(*param, *argument);
         }
 
-        self.visit_expression(ast, function.body);
+        self.visit_expression(ast, body);
         self.pop_frame();
     }
 
     fn visit_assignment_expression(&mut self, ast: &mut Ast, assign_expr: &AssignExpr, expr: &Expr) {
         self.visit_expression(ast, assign_expr.expression);
+        if self.has_failed() {
+            return;
+        }
         self.frames.update(assign_expr.variable_idx, self.last_value.unwrap());
     }
 
+    fn visit_discard_expression(&mut self, ast: &mut Ast, discard_expr: &DiscardExpr, expr: &Expr) {
+        self.visit_expression(ast, discard_expr.expression);
+        self.last_value = None;
+    }
+
     fn visit_variable_expression(&mut self, ast: &mut Ast, var_expr: &VarExpr, expr: &Expr) {
-        let identifier = &var_expr.identifier.span.literal;
-        self.last_value = Some(*self.frames.get(&var_expr.variable_idx).expect(format!("Variable {} '{}' not found", var_expr.variable_idx.as_index(),  identifier).as_str()));
+        // a hoisted function can be called before a global it reads has been initialized
+        match self.frames.get(&var_expr.variable_idx) {
+            Some(value) => self.last_value = Some(*value),
+            None if self.global_scope.predeclared_globals.contains(&var_expr.variable_idx) => {
+                let identifier = &var_expr.identifier.span.literal;
+                self.runtime_error = Some(RuntimeError::new(format!("Predeclared global '{}' has not been set by the host", identifier)));
+            }
+            None => {
+                let identifier = &var_expr.identifier.span.literal;
+                self.runtime_error = Some(RuntimeError::new(format!("Variable '{}' is used before it is initialized", identifier)));
+            }
+        }
     }
 
     fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {
@@ -225,6 +400,9 @@ impl<'a> ASTVisitor for ASTEvaluator<'a> {
 
     fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
         self.visit_expression(ast, unary_expression.operand);
+        if self.has_failed() {
+            return;
+        }
         let operand = self.expect_last_value().expect_number();
         self.last_value = Some(Value::Number(match unary_expression.operator.kind {
             UnOpKid::Minus => -operand,
@@ -234,8 +412,14 @@ impl<'a> ASTVisitor for ASTEvaluator<'a> {
 
     fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expr: &BinaryExpr, expr: &Expr) {
         self.visit_expression(ast, binary_expr.left);
+        if self.has_failed() {
+            return;
+        }
         let left = self.expect_last_value();
         self.visit_expression(ast, binary_expr.right);
+        if self.has_failed() {
+            return;
+        }
         let right = self.expect_last_value();
         self.last_value = Some(match binary_expr.operator.kind {
             BinOpKind::Plus => Value::Number(left.expect_number() + right.expect_number()),
@@ -258,4 +442,202 @@ impl<'a> ASTVisitor for ASTEvaluator<'a> {
     fn visit_parenthesized_expression(&mut self, ast: &mut Ast, parenthesized_expression: &ParenthesizedExpr, expr: &Expr) {
         self.visit_expression(ast, parenthesized_expression.expression);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use crate::ast::evaluator::{ASTEvaluator, RuntimeError, Value};
+    use crate::compilation_unit::{CompilationOptions, CompilationUnit};
+    use crate::typings::Type;
+
+    #[test]
+    pub fn should_report_missing_host_implementation_when_calling_extern_function() {
+        let input = "\
+        extern func now() -> int
+        let a = now()
+        ";
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+
+        assert_eq!(
+            compilation_unit.evaluate(),
+            Err(RuntimeError::new("No host implementation for extern function 'now'".to_string())),
+        );
+    }
+
+    #[test]
+    pub fn should_call_registered_host_function() {
+        let input = "\
+        extern func add(a: int, b: int) -> int
+        let x = 2
+        let y = 3
+        add(x, y)
+        ";
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        eval.register_host_fn("add", Box::new(|arguments| {
+            Ok(Value::Number(arguments[0].expect_number() + arguments[1].expect_number()))
+        }));
+        compilation_unit.ast.visit(&mut eval);
+
+        assert_eq!(eval.last_value, Some(Value::Number(5)));
+    }
+
+    #[test]
+    pub fn should_call_extern_function_through_variable() {
+        let input = "\
+        extern func add(a: int, b: int) -> int
+        let g = add
+        g(2, 3)
+        ";
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        eval.register_host_fn("add", Box::new(|arguments| {
+            Ok(Value::Number(arguments[0].expect_number() + arguments[1].expect_number()))
+        }));
+
+        assert_eq!(eval.evaluate(&mut compilation_unit.ast), Ok(Some(Value::Number(5))));
+    }
+
+    #[test]
+    pub fn should_pass_widened_int_to_float_parameter_of_host_function() {
+        let input = "\
+        extern func half(x: float) -> int
+        half(3)
+        ";
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        eval.register_host_fn("half", Box::new(|arguments| Ok(Value::Number(arguments[0].expect_number() / 2))));
+
+        assert_eq!(eval.evaluate(&mut compilation_unit.ast), Ok(Some(Value::Number(1))));
+    }
+
+    #[test]
+    pub fn should_return_error_of_failing_host_function() {
+        let input = "\
+        extern func fail() -> int
+        let a = fail() + 1
+        let b = 2
+        ";
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        eval.register_host_fn("fail", Box::new(|_| Err(RuntimeError::new("host failed".to_string()))));
+
+        assert_eq!(eval.evaluate(&mut compilation_unit.ast), Err(RuntimeError::new("host failed".to_string())));
+        assert_eq!(eval.last_value, None);
+    }
+
+    #[test]
+    pub fn should_return_error_on_host_function_arity_mismatch() {
+        let input = "\
+        extern func add(a: int, b: int) -> int
+        ";
+
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        eval.register_host_fn("add", Box::new(|_| Ok(Value::Number(0))));
+        let function = compilation_unit.global_scope.functions.iter().next().expect("Expected a function");
+
+        assert_eq!(
+            eval.call_host_function(function, &[Value::Number(1)]),
+            Err(RuntimeError::new("Host function 'add' expects 2 arguments, but was given 1".to_string())),
+        );
+    }
+
+    #[test]
+    pub fn should_evaluate_discarded_expression_for_effects() {
+        let input = "\
+        extern func tick() -> int
+        _ = tick()
+        ";
+        let calls = Rc::new(Cell::new(0));
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        let counter = Rc::clone(&calls);
+        eval.register_host_fn("tick", Box::new(move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Value::Number(1))
+        }));
+        compilation_unit.ast.visit(&mut eval);
+
+        assert_eq!(calls.get(), 1);
+        assert_eq!(eval.last_value, None);
+    }
+
+    #[test]
+    pub fn should_return_error_when_hoisted_function_reads_uninitialized_global() {
+        let input = "\
+        let a = f()
+        let b = 1
+        let f = func -> int {
+            return b
+        }
+        ";
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+
+        assert_eq!(
+            eval.evaluate(&mut compilation_unit.ast),
+            Err(RuntimeError::new("Variable 'b' is used before it is initialized".to_string())),
+        );
+    }
+
+    #[test]
+    pub fn should_read_predeclared_global_set_by_host() {
+        let options = CompilationOptions {
+            predeclared_globals: vec![("VERSION".to_string(), Type::Int)],
+            ..Default::default()
+        };
+
+        let mut compilation_unit = CompilationUnit::compile_with_options("VERSION + 1", &options).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        eval.set_global("VERSION", Value::Number(2)).expect("Failed to set global");
+
+        assert_eq!(eval.evaluate(&mut compilation_unit.ast), Ok(Some(Value::Number(3))));
+    }
+
+    #[test]
+    pub fn should_return_error_when_predeclared_global_is_not_set() {
+        let options = CompilationOptions {
+            predeclared_globals: vec![("VERSION".to_string(), Type::Int)],
+            ..Default::default()
+        };
+
+        let mut compilation_unit = CompilationUnit::compile_with_options("VERSION + 1", &options).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+
+        assert_eq!(eval.set_global("VERSION", Value::Boolean(true)), Err(RuntimeError::new("Predeclared global 'VERSION' is of type 'int'".to_string())));
+        assert_eq!(
+            eval.evaluate(&mut compilation_unit.ast),
+            Err(RuntimeError::new("Predeclared global 'VERSION' has not been set by the host".to_string())),
+        );
+    }
+
+    #[test]
+    pub fn should_exit_loop_on_break() {
+        let input = "\
+        let i = 0
+        while i < 10 {
+            if i == 3 {
+                break
+            }
+            i = i + 1
+        }
+        i
+        ";
+
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut eval = ASTEvaluator::new(&compilation_unit.global_scope);
+        compilation_unit.ast.visit(&mut eval);
+
+        assert_eq!(eval.last_value, Some(Value::Number(3)));
+    }
+}
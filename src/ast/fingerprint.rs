@@ -0,0 +1,180 @@
+use crate::ast::{Ast, ExprId, ExprKind, FunctionDeclaration, NodeId, StmtId, StmtKind};
+use crate::stable_hash::StableHasher;
+
+impl Ast {
+    /// Returns a structural hash of the `func` expression `func_expr`: its parameters, return type
+    /// and body.
+    ///
+    /// Only node kinds, names and literal values contribute to the hash, so two functions that
+    /// differ in whitespace or position produce the same fingerprint. The hash is stable across
+    /// compiler builds, so fingerprints can be stored between runs.
+    pub fn function_fingerprint(&self, func_expr: ExprId) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.hash_node(NodeId::Expr(func_expr), &mut hasher);
+        hasher.finish()
+    }
+
+    /// Hashes the node itself, then the number of its children and each child in source order.
+    fn hash_node(&self, node: NodeId, hasher: &mut StableHasher) {
+        match node {
+            NodeId::Expr(expr_id) => self.hash_expr(expr_id, hasher),
+            NodeId::Stmt(stmt_id) => self.hash_stmt(stmt_id, hasher),
+        }
+        let children = self.children(node);
+        hasher.write_usize(children.len());
+        for child in children {
+            self.hash_node(child, hasher);
+        }
+    }
+
+    /// Hashes the kind of an expression and the names and values it holds, but not its children.
+    fn hash_expr(&self, expr_id: ExprId, hasher: &mut StableHasher) {
+        let kind = &self.query_expr(expr_id).kind;
+        match kind {
+            ExprKind::Number(number) => {
+                hasher.write_u8(0);
+                hasher.write_u64(number.number as u64);
+            }
+            ExprKind::Binary(binary) => {
+                hasher.write_u8(1);
+                hasher.write_str(&binary.operator.token.span.literal);
+            }
+            ExprKind::Unary(unary) => {
+                hasher.write_u8(2);
+                hasher.write_str(&unary.operator.token.span.literal);
+            }
+            ExprKind::Parenthesized(_) => hasher.write_u8(3),
+            ExprKind::Variable(variable) => {
+                hasher.write_u8(4);
+                hasher.write_str(&variable.identifier.span.literal);
+            }
+            ExprKind::Assignment(assignment) => {
+                hasher.write_u8(5);
+                hasher.write_str(&assignment.identifier.span.literal);
+            }
+            ExprKind::Discard(_) => hasher.write_u8(6),
+            ExprKind::Boolean(boolean) => {
+                hasher.write_u8(7);
+                hasher.write_bool(boolean.value);
+            }
+            ExprKind::Call(_) => hasher.write_u8(8),
+            ExprKind::If(if_expr) => {
+                hasher.write_u8(9);
+                hasher.write_bool(if_expr.else_branch.is_some());
+            }
+            ExprKind::Block(_) => hasher.write_u8(10),
+            ExprKind::Func(func) => {
+                hasher.write_u8(11);
+                Self::hash_function_signature(&func.decl, hasher);
+            }
+            ExprKind::Rec(_) => hasher.write_u8(12),
+            ExprKind::Error(span) => {
+                hasher.write_u8(13);
+                hasher.write_str(&span.literal);
+            }
+        }
+    }
+
+    /// Hashes the kind of a statement and the names it holds, but not its children.
+    fn hash_stmt(&self, stmt_id: StmtId, hasher: &mut StableHasher) {
+        let kind = &self.query_stmt(stmt_id).kind;
+        match kind {
+            StmtKind::Expr(_) => hasher.write_u8(0),
+            StmtKind::Let(let_stmt) => {
+                hasher.write_u8(1);
+                hasher.write_str(&let_stmt.identifier.span.literal);
+                Self::hash_optional_str(let_stmt.type_annotation.as_ref().map(|annotation| annotation.type_name.span.literal.as_str()), hasher);
+            }
+            StmtKind::While(_) => hasher.write_u8(2),
+            StmtKind::Return(return_stmt) => {
+                hasher.write_u8(3);
+                hasher.write_bool(return_stmt.return_value.is_some());
+            }
+            StmtKind::Extern(extern_stmt) => {
+                hasher.write_u8(4);
+                hasher.write_str(&extern_stmt.identifier.span.literal);
+                Self::hash_function_signature(&extern_stmt.decl, hasher);
+            }
+            StmtKind::Break(_) => hasher.write_u8(5),
+            StmtKind::Continue(_) => hasher.write_u8(6),
+        }
+    }
+
+    fn hash_function_signature(decl: &FunctionDeclaration, hasher: &mut StableHasher) {
+        hasher.write_usize(decl.parameters.len());
+        for parameter in &decl.parameters {
+            hasher.write_str(&parameter.identifier.span.literal);
+            hasher.write_str(&parameter.type_annotation.type_name.span.literal);
+        }
+        Self::hash_optional_str(decl.return_type.as_ref().map(|return_type| return_type.type_name.span.literal.as_str()), hasher);
+    }
+
+    fn hash_optional_str(value: Option<&str>, hasher: &mut StableHasher) {
+        hasher.write_bool(value.is_some());
+        if let Some(value) = value {
+            hasher.write_str(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::printer::test::parse;
+    use crate::ast::{Ast, ExprId, ExprKind};
+    use crate::compilation_unit::CompilationUnit;
+
+    fn fingerprints(input: &str) -> Vec<u64> {
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        fingerprints_of(&compilation_unit.ast)
+    }
+
+    fn fingerprints_of(ast: &Ast) -> Vec<u64> {
+        let functions: Vec<ExprId> = ast.expressions.iter()
+            .filter(|expr| matches!(expr.kind, ExprKind::Func(_)))
+            .map(|expr| expr.id)
+            .collect();
+        functions.iter().map(|func_expr| ast.function_fingerprint(*func_expr)).collect()
+    }
+
+    #[test]
+    pub fn should_ignore_spans_and_whitespace() {
+        let input = "\
+        let f = func (a: int) -> int { return a * 2 + 1 }
+        let g = func (a: int)   ->   int {
+            return a*2 +1
+        }
+        ";
+
+        let fingerprints = fingerprints(input);
+
+        assert_eq!(fingerprints.len(), 2);
+        assert_eq!(fingerprints[0], fingerprints[1]);
+    }
+
+    #[test]
+    pub fn should_change_when_empty_else_branch_is_added() {
+        let input = "\
+        let f = func (c: bool) { if c {} }
+        let g = func (c: bool) { if c {} else {} }
+        ";
+
+        // parsed only, the resolver rejects an empty else branch after an empty then branch
+        let fingerprints = fingerprints_of(&parse(input));
+
+        assert_eq!(fingerprints.len(), 2);
+        assert_ne!(fingerprints[0], fingerprints[1]);
+    }
+
+    #[test]
+    pub fn should_change_when_literal_changes() {
+        let input = "\
+        let f = func (a: int) -> int { return a * 2 + 1 }
+        let g = func (a: int) -> int { return a * 2 + 3 }
+        ";
+
+        let fingerprints = fingerprints(input);
+
+        assert_eq!(fingerprints.len(), 2);
+        assert_ne!(fingerprints[0], fingerprints[1]);
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use crate::diagnostics::DiagnosticsBagCell;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
     // Literals
@@ -31,6 +33,9 @@ pub enum TokenKind {
     Func,
     Return,
     Rec,
+    Extern,
+    Break,
+    Continue,
     // Seperators
     LeftParen,
     RightParen,
@@ -40,6 +45,7 @@ pub enum TokenKind {
     Colon,
     SemiColon,
     Arrow,
+    Underscore,
     // Other
     Eof,
     Bad,
@@ -76,8 +82,8 @@ impl Display for TokenKind {
             TokenKind::LessThanEquals => write!(f, "<="),
             TokenKind::EqualsEquals => write!(f, "=="),
             TokenKind::BangEquals => write!(f, "!="),
-            TokenKind::OpenBrace => write!(f, "("),
-            TokenKind::CloseBrace => write!(f, ")"),
+            TokenKind::OpenBrace => write!(f, "{{"),
+            TokenKind::CloseBrace => write!(f, "}}"),
             TokenKind::True => write!(f, "True"),
             TokenKind::False => write!(f, "False"),
             TokenKind::While => write!(f, "While"),
@@ -87,7 +93,11 @@ impl Display for TokenKind {
             TokenKind::Colon => write!(f, "Colon"),
             TokenKind::Arrow => write!(f, "Arrow"),
             TokenKind::SemiColon => write!(f, "SemiColon"),
+            TokenKind::Underscore => write!(f, "_"),
             TokenKind::Rec => write!(f, "Rec"),
+            TokenKind::Extern => write!(f, "Extern"),
+            TokenKind::Break => write!(f, "Break"),
+            TokenKind::Continue => write!(f, "Continue"),
         }
     }
 }
@@ -125,36 +135,62 @@ impl Token {
 pub struct Lexer<'a> {
     input: &'a str,
     current_pos: usize,
+    /// Position of `input` in the whole source, added to every span.
+    offset: usize,
+    diagnostics_bag: DiagnosticsBagCell,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str, diagnostics_bag: DiagnosticsBagCell) -> Self {
+        Self::with_offset(input, 0, diagnostics_bag)
+    }
+
+    /// Lexes `input` as the part of a larger source starting at `offset`.
+    pub fn with_offset(input: &'a str, offset: usize, diagnostics_bag: DiagnosticsBagCell) -> Self {
         Self {
             input,
             current_pos: 0,
+            offset,
+            diagnostics_bag,
         }
     }
 
+    /// Returns the next token, or `None` once the end of input token has been returned.
+    ///
+    /// Characters that do not start any token are reported and skipped, so lexing continues
+    /// with the rest of the input.
     pub fn next_token(&mut self) -> Option<Token> {
+        loop {
+            let token = self.lex_token()?;
+            if token.kind != TokenKind::Bad {
+                return Some(token);
+            }
+            self.diagnostics_bag.borrow_mut().report_unexpected_character(&token);
+        }
+    }
+
+    fn lex_token(&mut self) -> Option<Token> {
         if self.current_pos == self.input.len() {
-            let eof_char = '\0';
+            let eof_pos = self.offset + self.current_pos;
             self.current_pos += 1;
             return Some(Token::new(
                 TokenKind::Eof,
-                TextSpan::new(0, 0, eof_char.to_string()),
+                TextSpan::new(eof_pos, eof_pos, String::new()),
             ));
         }
         let c = self.current_char();
         return c.map(|c| {
             let start = self.current_pos;
             let mut kind = TokenKind::Bad;
+            let mut number_overflowed = false;
             if Self::is_number_start(&c) {
-                let number: i64 = self.consume_number();
-                kind = TokenKind::Number(number);
+                let number = self.consume_number();
+                number_overflowed = number.is_none();
+                kind = TokenKind::Number(number.unwrap_or(0));
             } else if Self::is_whitespace(&c) {
                 self.consume();
                 kind = TokenKind::Whitespace;
-            } else if Self::is_identifier_start(&c) {
+            } else if Self::is_identifier_start(&c) || self.is_underscore_prefixed_identifier(&c) {
                 let identifier = self.consume_identifier();
                 kind = match identifier.as_str() {
                     "let" => TokenKind::Let,
@@ -166,6 +202,9 @@ impl<'a> Lexer<'a> {
                     "func" => TokenKind::Func,
                     "return" => TokenKind::Return,
                     "rec" => TokenKind::Rec,
+                    "extern" => TokenKind::Extern,
+                    "break" => TokenKind::Break,
+                    "continue" => TokenKind::Continue,
                     _ => TokenKind::Identifier,
                 }
             } else {
@@ -173,8 +212,12 @@ impl<'a> Lexer<'a> {
             }
             let end = self.current_pos;
             let literal = self.input[start..end].to_string();
-            let span = TextSpan::new(start, end, literal);
-            Token::new(kind, span)
+            let span = TextSpan::new(self.offset + start, self.offset + end, literal);
+            let token = Token::new(kind, span);
+            if number_overflowed {
+                self.diagnostics_bag.borrow_mut().report_number_too_large(&token);
+            }
+            token
         });
     }
 
@@ -218,6 +261,7 @@ impl<'a> Lexer<'a> {
             ',' => TokenKind::Comma,
             ':' => TokenKind::Colon,
             ';' => TokenKind::SemiColon,
+            '_' => TokenKind::Underscore,
 
             _ => TokenKind::Bad,
         }
@@ -248,7 +292,7 @@ impl<'a> Lexer<'a> {
     fn consume_identifier(&mut self) -> String {
         let mut identifier = String::new();
         while let Some(c) = self.current_char() {
-            if Self::is_identifier_start(&c) {
+            if Self::is_identifier_start(&c) || c == '_' {
                 self.consume().unwrap();
                 identifier.push(c);
             } else {
@@ -262,31 +306,67 @@ impl<'a> Lexer<'a> {
         c.is_alphabetic()
     }
 
+    /// Whether `c` is an underscore starting an identifier such as `_unused`, rather than the
+    /// discard `_`.
+    fn is_underscore_prefixed_identifier(&self, c: &char) -> bool {
+        *c == '_' && self.peek_char(1).map_or(false, |next| next.is_alphabetic() || next == '_')
+    }
+
     fn is_number_start(c: &char) -> bool {
         c.is_digit(10)
     }
 
     fn current_char(&self) -> Option<char> {
-        self.input.chars().nth(self.current_pos)
+        self.peek_char(0)
+    }
+
+    /// Returns the character `offset` characters after the current one.
+    ///
+    /// Positions are byte offsets into the input, so this never splits a multi-byte character.
+    fn peek_char(&self, offset: usize) -> Option<char> {
+        self.input.get(self.current_pos..)?.chars().nth(offset)
     }
 
     fn consume(&mut self) -> Option<char> {
-        if self.current_pos >= self.input.len() {
-            return None;
-        }
-        let c = self.current_char();
-        self.current_pos += 1;
-        c
+        let c = self.current_char()?;
+        self.current_pos += c.len_utf8();
+        Some(c)
     }
-    fn consume_number(&mut self) -> i64 {
-        let mut number: i64 = 0;
-        while let Some(c) = self.consume() {
-            if c.is_digit(10) {
-                number = number * 10 + c.to_digit(10).unwrap() as i64;
-            } else {
-                break;
+
+    /// Consumes a number literal, returning `None` if its value does not fit into an `i64`.
+    ///
+    /// The whole literal is consumed either way.
+    fn consume_number(&mut self) -> Option<i64> {
+        let radix = self.consume_radix_prefix();
+        let mut number: Option<i64> = Some(0);
+        while let Some(c) = self.current_char() {
+            match c.to_digit(radix) {
+                Some(digit) => {
+                    self.consume();
+                    number = number
+                        .and_then(|number| number.checked_mul(radix as i64))
+                        .and_then(|number| number.checked_add(digit as i64));
+                }
+                None => break,
             }
         }
         number
     }
+
+    /// Consumes a `0x` or `0b` prefix if it is followed by a digit of that base, returning the
+    /// radix of the literal.
+    fn consume_radix_prefix(&mut self) -> u32 {
+        let radix = match (self.current_char(), self.peek_char(1)) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('b')) => 2,
+            _ => return 10,
+        };
+        let has_digit = self.peek_char(2).map_or(false, |c| c.is_digit(radix));
+        if !has_digit {
+            return 10;
+        }
+        self.consume();
+        self.consume();
+        radix
+    }
 }
@@ -0,0 +1,183 @@
+use crate::ast::node_data::NodeData;
+use crate::ast::{Ast, ExprId, ExprKind, FuncDeclParameter, StmtId, StmtKind};
+
+/// Computes how many characters wide a node is when printed on a single line without colors.
+///
+/// Expressions measure exactly like the [`ASTPrinter`](crate::ast::printer::ASTPrinter) prints
+/// them. Blocks, which the printer spreads over several lines, are measured in their compact
+/// form `{ a; b }`. Widths are memoized, so measuring a parent after its children is cheap.
+pub struct MeasureVisitor {
+    widths: NodeData<usize>,
+}
+
+impl MeasureVisitor {
+    pub fn new() -> Self {
+        Self { widths: NodeData::new() }
+    }
+
+    pub fn measure_expr(&mut self, ast: &Ast, expr_id: ExprId) -> usize {
+        if let Some(width) = self.widths.get(expr_id) {
+            return *width;
+        }
+        let width = self.compute_expr_width(ast, expr_id);
+        self.widths.set(expr_id, width);
+        width
+    }
+
+    pub fn measure_stmt(&mut self, ast: &Ast, stmt_id: StmtId) -> usize {
+        if let Some(width) = self.widths.get(stmt_id) {
+            return *width;
+        }
+        let width = self.compute_stmt_width(ast, stmt_id);
+        self.widths.set(stmt_id, width);
+        width
+    }
+
+    /// Number of widths computed so far.
+    pub fn measured_count(&self) -> usize {
+        self.widths.iter().count()
+    }
+
+    fn compute_expr_width(&mut self, ast: &Ast, expr_id: ExprId) -> usize {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Number(number) => Self::text_width(number.literal()),
+            ExprKind::Binary(binary) => {
+                let operator = Self::text_width(&binary.operator.token.span.literal);
+                self.measure_expr(ast, binary.left) + 1 + operator + 1 + self.measure_expr(ast, binary.right)
+            }
+            ExprKind::Unary(unary) => {
+                Self::text_width(&unary.operator.token.span.literal) + self.measure_expr(ast, unary.operand)
+            }
+            ExprKind::Parenthesized(parenthesized) => 1 + self.measure_expr(ast, parenthesized.expression) + 1,
+            ExprKind::Variable(variable) => Self::text_width(&variable.identifier.span.literal),
+            ExprKind::Assignment(assignment) => {
+                Self::text_width(&assignment.identifier.span.literal) + " = ".len() + self.measure_expr(ast, assignment.expression)
+            }
+            ExprKind::Discard(discard) => "_ = ".len() + self.measure_expr(ast, discard.expression),
+            ExprKind::Boolean(boolean) => if boolean.value { "true".len() } else { "false".len() },
+            ExprKind::Call(call) => {
+                let arguments: usize = call.arguments.iter().map(|argument| self.measure_expr(ast, *argument)).sum();
+                let separators = call.arguments.len().saturating_sub(1) * ", ".len();
+                self.measure_expr(ast, call.callee) + 1 + arguments + separators + 1
+            }
+            ExprKind::If(if_expr) => {
+                let mut width = "if ".len() + self.measure_expr(ast, if_expr.condition) + 1 + self.measure_expr(ast, if_expr.then_branch);
+                if let Some(else_branch) = &if_expr.else_branch {
+                    width += " else ".len() + self.measure_expr(ast, else_branch.expr);
+                }
+                width
+            }
+            ExprKind::Block(block) => {
+                if block.stmts.is_empty() {
+                    return "{}".len();
+                }
+                let statements: usize = block.stmts.iter().map(|stmt| self.measure_stmt(ast, *stmt)).sum();
+                let separators = (block.stmts.len() - 1) * "; ".len();
+                "{ ".len() + statements + separators + " }".len()
+            }
+            ExprKind::Func(func) => {
+                let parameters = if func.decl.parameters.is_empty() {
+                    0
+                } else {
+                    1 + Self::parameters_width(&func.decl.parameters) + 1
+                };
+                let return_type = func.decl.return_type.as_ref()
+                    .map(|return_type| "-> ".len() + Self::text_width(&return_type.type_name.span.literal) + 1)
+                    .unwrap_or(0);
+                let body = func.decl.body.map(|body| self.measure_expr(ast, body)).unwrap_or(0);
+                "func ".len() + parameters + 1 + return_type + body
+            }
+            ExprKind::Rec(_) => "rec".len(),
+            ExprKind::Error(span) => "<error: ".len() + Self::text_width(&span.literal) + ">".len(),
+        }
+    }
+
+    fn compute_stmt_width(&mut self, ast: &Ast, stmt_id: StmtId) -> usize {
+        match &ast.query_stmt(stmt_id).kind {
+            StmtKind::Expr(expr_id) => self.measure_expr(ast, *expr_id),
+            StmtKind::Let(let_stmt) => {
+                let annotation = let_stmt.type_annotation.as_ref()
+                    .map(|annotation| ": ".len() + Self::text_width(&annotation.type_name.span.literal))
+                    .unwrap_or(0);
+                "let ".len() + Self::text_width(&let_stmt.identifier.span.literal) + annotation + " = ".len()
+                    + self.measure_expr(ast, let_stmt.initializer)
+            }
+            StmtKind::While(while_stmt) => {
+                "while ".len() + self.measure_expr(ast, while_stmt.condition) + 1 + self.measure_expr(ast, while_stmt.body)
+            }
+            StmtKind::Return(return_stmt) => {
+                "return".len() + return_stmt.return_value.map(|value| 1 + self.measure_expr(ast, value)).unwrap_or(0)
+            }
+            StmtKind::Extern(extern_stmt) => {
+                let return_type = extern_stmt.decl.return_type.as_ref()
+                    .map(|return_type| " -> ".len() + Self::text_width(&return_type.type_name.span.literal))
+                    .unwrap_or(0);
+                "extern func ".len() + Self::text_width(&extern_stmt.identifier.span.literal)
+                    + 1 + Self::parameters_width(&extern_stmt.decl.parameters) + 1 + return_type
+            }
+            StmtKind::Break(_) => "break".len(),
+            StmtKind::Continue(_) => "continue".len(),
+        }
+    }
+
+    fn parameters_width(parameters: &[FuncDeclParameter]) -> usize {
+        let parameters_width: usize = parameters.iter().map(|parameter| {
+            Self::text_width(&parameter.identifier.span.literal) + ": ".len()
+                + Self::text_width(&parameter.type_annotation.type_name.span.literal)
+        }).sum();
+        parameters_width + parameters.len().saturating_sub(1) * ", ".len()
+    }
+
+    fn text_width(text: &str) -> usize {
+        text.chars().count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::measure::MeasureVisitor;
+    use crate::ast::printer::ASTPrinter;
+    use crate::ast::printer::test::strip_colors;
+    use crate::ast::visitor::ASTVisitor;
+    use crate::ast::StmtKind;
+    use crate::compilation_unit::CompilationUnit;
+
+    #[test]
+    pub fn should_measure_width_of_compact_printed_form() {
+        let mut compilation_unit = CompilationUnit::compile("let a = 1 + 22").expect("Failed to compile");
+        let ast = &mut compilation_unit.ast;
+        let initializer = ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) => Some(let_stmt.initializer),
+            _ => None,
+        }).expect("Expected a let statement");
+        let mut printer = ASTPrinter::new();
+        printer.visit_expression(ast, initializer);
+        let printed = strip_colors(&printer.result);
+
+        let mut measure = MeasureVisitor::new();
+
+        assert_eq!(printed, "1 + 22");
+        assert_eq!(measure.measure_expr(ast, initializer), printed.len());
+        assert_eq!(measure.measured_count(), 3);
+        assert_eq!(measure.measure_expr(ast, initializer), printed.len());
+        assert_eq!(measure.measured_count(), 3);
+    }
+
+    #[test]
+    pub fn should_measure_width_of_function_with_return_type() {
+        let mut compilation_unit = CompilationUnit::compile("let f = func (a: int) -> int a").expect("Failed to compile");
+        let ast = &mut compilation_unit.ast;
+        let initializer = ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) => Some(let_stmt.initializer),
+            _ => None,
+        }).expect("Expected a let statement");
+        let mut printer = ASTPrinter::new();
+        printer.visit_expression(ast, initializer);
+        let printed = strip_colors(&printer.result);
+
+        let mut measure = MeasureVisitor::new();
+
+        assert_eq!(printed, "func (a: int) -> int a");
+        assert_eq!(measure.measure_expr(ast, initializer), printed.len());
+    }
+}
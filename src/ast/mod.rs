@@ -3,8 +3,10 @@ use rs_compiler::{Idx, idx, IdxVec};
 use printer::ASTPrinter;
 use visitor::ASTVisitor;
 
+pub use crate::ast::node_data::NodeId;
 use crate::ast::lexer::Token;
-use crate::compilation_unit::VariableIdx;
+use crate::compilation_unit::{FunctionIdx, VariableIdx};
+use crate::passes::constant_folder::ConstantFolder;
 use crate::text::span::TextSpan;
 use crate::typings::Type;
 
@@ -13,13 +15,23 @@ pub mod parser;
 pub mod evaluator;
 pub mod visitor;
 pub mod printer;
+pub mod node_data;
+pub mod measure;
+pub mod sexpr_printer;
+pub mod stream_lexer;
+pub mod def_use;
+mod children;
+mod scope;
+mod fingerprint;
+mod enclosing_function;
+#[cfg(test)]
+mod recovery_snapshots;
 
 idx!(StmtId);
 idx!(ExprId);
 idx!(ItemId);
 
-#[cfg_attr(test, derive(Clone))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Ast {
     pub statements: IdxVec<StmtId, Stmt>,
     pub expressions: IdxVec<ExprId, Expr>,
@@ -31,6 +43,11 @@ impl Ast {
         Self { statements: IdxVec::new(), expressions: IdxVec::new(), items: IdxVec::new() }
     }
 
+    /// Number of statements and expressions in the tree.
+    pub fn node_count(&self) -> usize {
+        self.statements.len() + self.expressions.len()
+    }
+
     pub fn query_item(&self, item_id: ItemId) -> &Item {
         &self.items[item_id]
     }
@@ -70,15 +87,76 @@ impl Ast {
             StmtKind::Let(var_decl) => {
                 var_decl.variable_idx = variable_idx;
             }
+            StmtKind::Extern(extern_stmt) => {
+                extern_stmt.variable_idx = variable_idx;
+            }
             _ => unreachable!("Cannot set variable of non-variable statement")
         }
     }
 
+    pub fn set_extern_function(&mut self, stmt_id: StmtId, function_idx: FunctionIdx) {
+        match &mut self.query_stmt_mut(stmt_id).kind {
+            StmtKind::Extern(extern_stmt) => extern_stmt.function_idx = function_idx,
+            _ => unreachable!("Cannot set function of non-extern statement"),
+        }
+    }
+
+    pub fn set_parameter_variables(&mut self, expr_id: ExprId, variables: &[VariableIdx]) {
+        let expr = self.query_expr_mut(expr_id);
+        match &mut expr.kind {
+            ExprKind::Func(func_expr) => func_expr.decl.set_parameter_variables(variables),
+            _ => unreachable!("Cannot set parameters of non-function expression")
+        }
+    }
+
+    pub fn set_extern_parameter_variables(&mut self, stmt_id: StmtId, variables: &[VariableIdx]) {
+        match &mut self.query_stmt_mut(stmt_id).kind {
+            StmtKind::Extern(extern_stmt) => extern_stmt.decl.set_parameter_variables(variables),
+            _ => unreachable!("Cannot set parameters of non-extern statement"),
+        }
+    }
+
+    /// Returns the span of the identifier declaring the variable that `use_site` resolved to.
+    ///
+    /// `use_site` is a variable or assignment expression of a resolved tree; `None` is returned
+    /// for any other expression.
+    pub fn definition_of(&self, use_site: ExprId) -> Option<TextSpan> {
+        let variable_idx = match &self.query_expr(use_site).kind {
+            ExprKind::Variable(var_expr) => var_expr.variable_idx,
+            ExprKind::Assignment(assign_expr) => assign_expr.variable_idx,
+            _ => return None,
+        };
+        let declared_by_statement = self.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) if let_stmt.variable_idx == variable_idx => Some(&let_stmt.identifier),
+            StmtKind::Extern(extern_stmt) if extern_stmt.variable_idx == variable_idx => Some(&extern_stmt.identifier),
+            _ => None,
+        });
+        let declared_by_parameter = || self.expressions.iter().find_map(|expr| match &expr.kind {
+            ExprKind::Func(func_expr) => func_expr.decl.parameters.iter()
+                .find(|parameter| parameter.variable_idx == Some(variable_idx))
+                .map(|parameter| &parameter.identifier),
+            _ => None,
+        });
+        declared_by_statement.or_else(declared_by_parameter).map(|identifier| identifier.span.clone())
+    }
+
     pub fn set_type(&mut self, expr_id: ExprId, ty: Type) {
         let expr = &mut self.expressions[expr_id];
         expr.ty = ty;
     }
 
+    pub fn set_kind(&mut self, expr_id: ExprId, kind: ExprKind) {
+        let expr = self.query_expr_mut(expr_id);
+        expr.kind = kind;
+    }
+
+    /// Whether `expr_id` is a compile-time constant: a literal, or parentheses and operators over
+    /// constants that the [`ConstantFolder`](crate::passes::constant_folder::ConstantFolder)
+    /// can fold. Any variable, call or assignment makes the expression non-constant.
+    pub fn is_constant(&self, expr_id: ExprId) -> bool {
+        ConstantFolder::evaluate_constant(self, expr_id).is_some()
+    }
+
     fn stmt_from_kind(&mut self, kind: StmtKind) -> &Stmt {
         let stmt = Stmt::new(kind, StmtId::new(0));
         let id = self.statements.push(stmt);
@@ -110,9 +188,27 @@ impl Ast {
         self.stmt_from_kind(StmtKind::Return(ReturnStmt { return_keyword, return_value }))
     }
 
+    pub fn break_statement(&mut self, break_keyword: Token) -> &Stmt {
+        self.stmt_from_kind(StmtKind::Break(BreakStmt { break_keyword }))
+    }
+
+    pub fn continue_statement(&mut self, continue_keyword: Token) -> &Stmt {
+        self.stmt_from_kind(StmtKind::Continue(ContinueStmt { continue_keyword }))
+    }
+
     pub fn func_expr(&mut self, func_keyword: Token, parameters: Vec<FuncDeclParameter>, body: ExprId, return_type: Option<FunctionReturnTypeSyntax>) -> &Expr {
         self.expr_from_kind(ExprKind::Func(FuncExpr {
-            decl: FunctionDeclaration { func_keyword, parameters, body, return_type }
+            decl: FunctionDeclaration { func_keyword, parameters, body: Some(body), return_type }
+        }))
+    }
+
+    pub fn extern_statement(&mut self, extern_keyword: Token, identifier: Token, func_keyword: Token, parameters: Vec<FuncDeclParameter>, return_type: Option<FunctionReturnTypeSyntax>) -> &Stmt {
+        self.stmt_from_kind(StmtKind::Extern(ExternStmt {
+            extern_keyword,
+            identifier,
+            decl: FunctionDeclaration { func_keyword, parameters, body: None, return_type },
+            variable_idx: VariableIdx::new(0),
+            function_idx: FunctionIdx::new(0),
         }))
     }
 
@@ -131,7 +227,8 @@ impl Ast {
     }
 
     pub fn number_expression(&mut self, token: Token, number: i64) -> &Expr {
-        self.expr_from_kind(ExprKind::Number(NumberExpr { number, token }))
+        let base = NumberBase::of_literal(&token.span.literal);
+        self.expr_from_kind(ExprKind::Number(NumberExpr { number, base, token }))
     }
 
     pub fn binary_expression(&mut self, operator: BinOperator, left: ExprId, right: ExprId) -> &Expr {
@@ -154,6 +251,10 @@ impl Ast {
         self.expr_from_kind(ExprKind::Assignment(AssignExpr { identifier, expression, equals, variable_idx: VariableIdx::new(0) }))
     }
 
+    pub fn discard_expression(&mut self, underscore: Token, equals: Token, expression: ExprId) -> &Expr {
+        self.expr_from_kind(ExprKind::Discard(DiscardExpr { underscore, equals, expression }))
+    }
+
     pub fn boolean_expression(&mut self, token: Token, value: bool) -> &Expr {
         self.expr_from_kind(ExprKind::Boolean(BoolExpr { token, value }))
     }
@@ -207,6 +308,9 @@ pub enum StmtKind {
     Let(LetStmt),
     While(WhileStmt),
     Return(ReturnStmt),
+    Extern(ExternStmt),
+    Break(BreakStmt),
+    Continue(ContinueStmt),
 }
 
 #[derive(Debug, Clone)]
@@ -215,6 +319,28 @@ pub struct ReturnStmt {
     pub return_value: Option<ExprId>,
 }
 
+#[derive(Debug, Clone)]
+pub struct BreakStmt {
+    pub break_keyword: Token,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContinueStmt {
+    pub continue_keyword: Token,
+}
+
+/// A function whose signature is known to the compiler but whose body is provided by the host,
+/// e.g. `extern func now() -> int`.
+#[derive(Debug, Clone)]
+pub struct ExternStmt {
+    pub extern_keyword: Token,
+    pub identifier: Token,
+    pub decl: FunctionDeclaration,
+    pub variable_idx: VariableIdx,
+    /// The declared function, set by the resolver.
+    pub function_idx: FunctionIdx,
+}
+
 #[derive(Debug, Clone)]
 pub struct StaticTypeAnnotation {
     pub colon: Token,
@@ -231,6 +357,8 @@ impl StaticTypeAnnotation {
 pub struct FuncDeclParameter {
     pub identifier: Token,
     pub type_annotation: StaticTypeAnnotation,
+    /// The variable the parameter declares, set by the resolver.
+    pub variable_idx: Option<VariableIdx>,
 }
 
 #[derive(Debug, Clone)]
@@ -249,10 +377,19 @@ impl FunctionReturnTypeSyntax {
 pub struct FunctionDeclaration {
     pub func_keyword: Token,
     pub parameters: Vec<FuncDeclParameter>,
-    pub body: ExprId,
+    /// `None` for extern declarations.
+    pub body: Option<ExprId>,
     pub return_type: Option<FunctionReturnTypeSyntax>,
 }
 
+impl FunctionDeclaration {
+    fn set_parameter_variables(&mut self, variables: &[VariableIdx]) {
+        for (parameter, variable_idx) in self.parameters.iter_mut().zip(variables) {
+            parameter.variable_idx = Some(*variable_idx);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WhileStmt {
     pub while_keyword: Token,
@@ -330,6 +467,19 @@ impl Stmt {
                 }
                 TextSpan::combine(spans)
             }
+            StmtKind::Extern(extern_stmt) => {
+                let mut spans = vec![
+                    extern_stmt.extern_keyword.span.clone(),
+                    extern_stmt.decl.func_keyword.span.clone(),
+                    extern_stmt.identifier.span.clone(),
+                ];
+                if let Some(return_type) = &extern_stmt.decl.return_type {
+                    spans.push(return_type.type_name.span.clone());
+                }
+                TextSpan::combine(spans)
+            }
+            StmtKind::Break(break_stmt) => break_stmt.break_keyword.span.clone(),
+            StmtKind::Continue(continue_stmt) => continue_stmt.continue_keyword.span.clone(),
         }
     }
 }
@@ -355,6 +505,7 @@ pub enum ExprKind {
     Assignment(
         AssignExpr
     ),
+    Discard(DiscardExpr),
     Boolean(
         BoolExpr
     ),
@@ -394,6 +545,14 @@ pub struct BoolExpr {
     pub token: Token,
 }
 
+/// `_ = expression`: evaluates the expression for its effects and drops the value.
+#[derive(Debug, Clone)]
+pub struct DiscardExpr {
+    pub underscore: Token,
+    pub equals: Token,
+    pub expression: ExprId,
+}
+
 #[derive(Debug, Clone)]
 pub struct AssignExpr {
     pub identifier: Token,
@@ -403,7 +562,7 @@ pub struct AssignExpr {
 
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UnOpKid {
     Minus,
     BitwiseNot,
@@ -440,7 +599,7 @@ impl VarExpr {
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BinOpKind {
     // Arithmetic
     Plus,
@@ -515,9 +674,45 @@ pub struct BinaryExpr {
 #[derive(Debug, Clone)]
 pub struct NumberExpr {
     pub number: i64,
+    pub base: NumberBase,
     pub token: Token,
 }
 
+impl NumberExpr {
+    /// Returns the literal as written in the source, or the value of a constant synthesized by a pass.
+    pub fn literal(&self) -> &str {
+        &self.token.span.literal
+    }
+}
+
+/// The base a number literal was written in, kept so the literal prints back the same way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberBase {
+    Decimal,
+    Hexadecimal,
+    Binary,
+}
+
+impl NumberBase {
+    pub fn of_literal(literal: &str) -> Self {
+        if literal.starts_with("0x") {
+            NumberBase::Hexadecimal
+        } else if literal.starts_with("0b") {
+            NumberBase::Binary
+        } else {
+            NumberBase::Decimal
+        }
+    }
+
+    pub fn format(&self, number: i64) -> String {
+        match self {
+            NumberBase::Decimal => number.to_string(),
+            NumberBase::Hexadecimal => format!("0x{:X}", number),
+            NumberBase::Binary => format!("0b{:b}", number),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ParenthesizedExpr {
     pub left_paren: Token,
@@ -573,6 +768,12 @@ impl Expr {
                 let expression = ast.query_expr(expr.expression).span(ast);
                 TextSpan::combine(vec![identifier, equals, expression])
             }
+            ExprKind::Discard(expr) => {
+                let underscore = expr.underscore.span.clone();
+                let equals = expr.equals.span.clone();
+                let expression = ast.query_expr(expr.expression).span(ast);
+                TextSpan::combine(vec![underscore, equals, expression])
+            }
             ExprKind::Boolean(expr) => expr.token.span.clone(),
             ExprKind::Call(expr) => {
                 let callee_span = ast.query_expr(expr.callee).span(ast);
@@ -609,7 +810,7 @@ impl Expr {
 
 #[cfg(test)]
 mod test {
-    use crate::ast::{AssignExpr, Ast, BinaryExpr, BlockExpr, BoolExpr, CallExpr, Expr, FunctionDeclaration, IfExpr, LetStmt, NumberExpr, ParenthesizedExpr, ReturnStmt, Stmt, UnaryExpr, VarExpr, WhileStmt};
+    use crate::ast::{AssignExpr, Ast, BinaryExpr, BlockExpr, BoolExpr, CallExpr, Expr, ExprKind, ExternStmt, FunctionDeclaration, IfExpr, LetStmt, NumberExpr, ParenthesizedExpr, ReturnStmt, Stmt, StmtKind, UnaryExpr, UnOpKid, VarExpr, WhileStmt};
     use crate::compilation_unit::CompilationUnit;
     use crate::text::span::TextSpan;
 
@@ -632,6 +833,7 @@ mod test {
         While,
         Return,
         Call,
+        Extern,
     }
 
     struct ASTVerifier {
@@ -671,6 +873,10 @@ mod test {
             self.visit_expression(ast, func_decl_statement.body);
         }
 
+        fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {
+            self.actual.push(TestASTNode::Extern);
+        }
+
         fn visit_return_statement(&mut self, ast: &mut Ast, return_statement: &ReturnStmt) {
             self.actual.push(TestASTNode::Return);
             if let Some(expression) = &return_statement.return_value {
@@ -844,6 +1050,28 @@ mod test {
         assert_tree(input, expected);
     }
 
+    #[test]
+    pub fn should_parse_bitwise_not_of_variable_as_unary_operator() {
+        let input = "\
+        let x = 1
+        let a = ~x";
+        let expected = vec![
+            TestASTNode::Let,
+            TestASTNode::Number(1),
+            TestASTNode::Let,
+            TestASTNode::Unary,
+            TestASTNode::Variable("x".to_string()),
+        ];
+
+        assert_tree(input, expected);
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let operators: Vec<UnOpKid> = compilation_unit.ast.expressions.iter().filter_map(|expr| match &expr.kind {
+            ExprKind::Unary(unary) => Some(unary.operator.kind.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(operators, vec![UnOpKid::BitwiseNot]);
+    }
+
     #[test]
     pub fn should_parse_bitwise_or() {
         let input = "let a = 1 | 2";
@@ -1054,4 +1282,62 @@ mod test {
 
         assert_tree(input, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn should_parse_extern_function_declaration() {
+        let input = "\
+        extern func now() -> int
+        let a = now()
+        ";
+        let expected = vec![
+            TestASTNode::Extern,
+            TestASTNode::Let,
+            TestASTNode::Call,
+        ];
+
+        assert_tree(input, expected);
+    }
+
+    #[test]
+    pub fn should_resolve_use_to_nearest_declaration() {
+        let input = "\
+        let x = 1
+        let f = func (x: int) -> int {
+            let x = x + 1
+            return x
+        }
+        let y = x
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let ast = &compilation_unit.ast;
+        let uses: Vec<_> = ast.expressions.iter()
+            .filter(|expr| matches!(&expr.kind, ExprKind::Variable(var_expr) if var_expr.identifier.span.literal == "x"))
+            .map(|expr| ast.definition_of(expr.id).expect("Use must have a definition").start)
+            .collect();
+
+        let outer = input.find("let x = 1").unwrap() + 4;
+        let parameter = input.find("(x: int)").unwrap() + 1;
+        let inner = input.find("let x = x").unwrap() + 4;
+        assert_eq!(uses, vec![parameter, inner, outer]);
+    }
+
+    #[test]
+    pub fn should_set_parameter_variables_of_extern_functions() {
+        let input = "\
+        let a = 1
+        extern func add(x: int, y: int) -> int
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let extern_stmt = compilation_unit.ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Extern(extern_stmt) => Some(extern_stmt),
+            _ => None,
+        }).expect("Expected an extern statement");
+
+        let variables: Vec<_> = extern_stmt.decl.parameters.iter().map(|parameter| parameter.variable_idx).collect();
+
+        let function = compilation_unit.global_scope.functions.get(extern_stmt.function_idx);
+        let expected: Vec<_> = function.parameters.iter().map(|variable_idx| Some(*variable_idx)).collect();
+        assert_eq!(variables, expected);
+        assert_eq!(variables.len(), 2);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::ast::{ExprId, StmtId};
+
+/// Identifies a statement or an expression of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeId {
+    Expr(ExprId),
+    Stmt(StmtId),
+}
+
+impl From<ExprId> for NodeId {
+    fn from(id: ExprId) -> Self {
+        NodeId::Expr(id)
+    }
+}
+
+impl From<StmtId> for NodeId {
+    fn from(id: StmtId) -> Self {
+        NodeId::Stmt(id)
+    }
+}
+
+/// A side table attaching a value of type `T` to expressions and statements.
+///
+/// Analyses can keep their results here instead of adding fields to the node structs.
+#[derive(Debug, Clone)]
+pub struct NodeData<T> {
+    data: HashMap<NodeId, T>,
+}
+
+impl<T> NodeData<T> {
+    pub fn new() -> Self {
+        Self { data: HashMap::new() }
+    }
+
+    pub fn get(&self, id: impl Into<NodeId>) -> Option<&T> {
+        self.data.get(&id.into())
+    }
+
+    /// Stores `value` for the node, returning the value it replaces.
+    pub fn set(&mut self, id: impl Into<NodeId>, value: T) -> Option<T> {
+        self.data.insert(id.into(), value)
+    }
+
+    /// Iterates over all stored entries in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.data.iter().map(|(id, value)| (*id, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rs_compiler::Idx;
+
+    use crate::ast::{ExprId, StmtId};
+    use crate::ast::node_data::{NodeData, NodeId};
+
+    #[test]
+    pub fn should_store_and_retrieve_value_per_node() {
+        let mut data: NodeData<usize> = NodeData::new();
+        let expr = ExprId::new(0);
+        let stmt = StmtId::new(0);
+
+        data.set(expr, 1);
+        data.set(stmt, 2);
+
+        assert_eq!(data.get(expr), Some(&1));
+        assert_eq!(data.get(stmt), Some(&2));
+        assert_eq!(data.get(ExprId::new(1)), None);
+
+        assert_eq!(data.set(expr, 3), Some(1));
+        let mut entries: Vec<(NodeId, usize)> = data.iter().map(|(id, value)| (id, *value)).collect();
+        entries.sort_by_key(|(_, value)| *value);
+        assert_eq!(entries, vec![(NodeId::Stmt(stmt), 2), (NodeId::Expr(expr), 3)]);
+    }
+}
@@ -35,6 +35,15 @@ pub struct Parser<'a> {
     current: Counter,
     diagnostics_bag: DiagnosticsBagCell,
     ast: &'a mut Ast,
+    /// Opening `(` and `{` tokens that have not been closed yet, innermost last.
+    delimiters: Vec<Token>,
+    /// Maximum number of expressions that may be nested inside each other.
+    ///
+    /// `None` means unlimited, which may overflow the stack on deeply nested input.
+    pub max_nesting_depth: Option<usize>,
+    nesting_depth: usize,
+    /// Set once the nesting limit was hit and the rest of the input skipped.
+    nesting_limit_exceeded: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -48,6 +57,10 @@ impl<'a> Parser<'a> {
             current: Counter::new(),
             diagnostics_bag,
             ast,
+            delimiters: Vec::new(),
+            max_nesting_depth: None,
+            nesting_depth: 0,
+            nesting_limit_exceeded: false,
         }
     }
 
@@ -55,6 +68,19 @@ impl<'a> Parser<'a> {
         while let Some(_) = self.next_item().map(|stmt| stmt.id) {}
     }
 
+    /// Parses like [`Parser::parse`] unless the input has more than `max_tokens` tokens, in which
+    /// case nothing is parsed and a diagnostic is reported at the first token over the limit.
+    pub fn parse_with_max_tokens(&mut self, max_tokens: usize) {
+        let token_count = self.tokens.iter().filter(|token| token.kind != TokenKind::Eof).count();
+        if token_count > max_tokens {
+            self.diagnostics_bag
+                .borrow_mut()
+                .report_too_many_tokens(&self.tokens[max_tokens], token_count, max_tokens);
+            return;
+        }
+        self.parse();
+    }
+
     fn next_item(&mut self) -> Option<&Item> {
         if self.is_at_end() {
             return None;
@@ -76,12 +102,25 @@ impl<'a> Parser<'a> {
             TokenKind::Let => self.parse_let_statement().id,
             TokenKind::While => self.parse_while_statement().id,
             TokenKind::Return => self.parse_return_statement().id,
+            TokenKind::Extern => self.parse_extern_statement().id,
+            TokenKind::Break => self.parse_break_statement().id,
+            TokenKind::Continue => self.parse_continue_statement().id,
             _ => self.parse_expression_statement().id,
         };
         self.consume_if(TokenKind::SemiColon);
         stmt
     }
 
+    fn parse_extern_statement(&mut self) -> &Stmt {
+        let extern_keyword = self.consume_and_check(TokenKind::Extern).clone();
+        let func_keyword = self.consume_and_check(TokenKind::Func).clone();
+        let identifier = self.consume_and_check(TokenKind::Identifier).clone();
+        let parameters = self.parse_optional_parameter_list();
+        let return_type = self.parse_optional_return_type();
+        self.ast
+            .extern_statement(extern_keyword, identifier, func_keyword, parameters, return_type)
+    }
+
     fn parse_func_expr(&mut self, func_keyword: Token) -> &Expr {
         let parameters = self.parse_optional_parameter_list();
         let return_type = self.parse_optional_return_type();
@@ -103,18 +142,20 @@ impl<'a> Parser<'a> {
         if self.current().kind != TokenKind::LeftParen {
             return Vec::new();
         }
-        self.consume_and_check(TokenKind::LeftParen);
+        let left_paren = self.consume_and_check(TokenKind::LeftParen).clone();
+        self.open_delimiter(left_paren);
         let mut parameters = Vec::new();
         while self.current().kind != TokenKind::RightParen && !self.is_at_end() {
             parameters.push(FuncDeclParameter {
                 identifier: self.consume_and_check(TokenKind::Identifier).clone(),
                 type_annotation: self.parse_type_annotation(),
+                variable_idx: None,
             });
             if self.current().kind == TokenKind::Comma {
                 self.consume_and_check(TokenKind::Comma);
             }
         }
-        self.consume_and_check(TokenKind::RightParen);
+        self.close_delimiter(TokenKind::RightParen);
         parameters
     }
 
@@ -125,6 +166,16 @@ impl<'a> Parser<'a> {
         self.ast.return_statement(return_keyword, Some(expression))
     }
 
+    fn parse_break_statement(&mut self) -> &Stmt {
+        let break_keyword = self.consume_and_check(TokenKind::Break).clone();
+        self.ast.break_statement(break_keyword)
+    }
+
+    fn parse_continue_statement(&mut self) -> &Stmt {
+        let continue_keyword = self.consume_and_check(TokenKind::Continue).clone();
+        self.ast.continue_statement(continue_keyword)
+    }
+
     fn parse_while_statement(&mut self) -> &Stmt {
         let while_keyword = self.consume_and_check(TokenKind::While).clone();
         let condition_expr = self.parse_expr();
@@ -134,11 +185,12 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_block_expression(&mut self, left_brace: Token) -> &Expr {
+        self.open_delimiter(left_brace.clone());
         let mut statements = Vec::new();
         while self.current().kind != TokenKind::CloseBrace && !self.is_at_end() {
             statements.push(self.parse_statement());
         }
-        let right_brace = self.consume_and_check(TokenKind::CloseBrace).clone();
+        let right_brace = self.close_delimiter(TokenKind::CloseBrace);
         self.ast
             .block_expression(left_brace, statements, right_brace)
     }
@@ -190,7 +242,38 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expr(&mut self) -> ExprId {
-        self.parse_assignment_expression()
+        self.parse_nested(Self::parse_assignment_expression)
+    }
+
+    /// Runs `parse` one nesting level deeper.
+    ///
+    /// Past [`Parser::max_nesting_depth`] the nesting is reported once and the rest of the input is
+    /// skipped. From then on every level returns an error expression, so the enclosing levels
+    /// unwind right away.
+    fn parse_nested(&mut self, parse: impl FnOnce(&mut Self) -> ExprId) -> ExprId {
+        let too_deep = self.max_nesting_depth.map_or(false, |max_depth| self.nesting_depth >= max_depth);
+        if too_deep || self.nesting_limit_exceeded {
+            return self.skip_too_deeply_nested();
+        }
+        self.nesting_depth += 1;
+        let expr = parse(self);
+        self.nesting_depth -= 1;
+        expr
+    }
+
+    fn skip_too_deeply_nested(&mut self) -> ExprId {
+        let token = self.current().clone();
+        if !self.nesting_limit_exceeded {
+            self.nesting_limit_exceeded = true;
+            let max_depth = self.max_nesting_depth.unwrap_or_default();
+            self.diagnostics_bag
+                .borrow_mut()
+                .report_nesting_too_deep(&token, max_depth);
+        }
+        while !self.is_at_end() {
+            self.consume();
+        }
+        self.ast.error_expression(token.span).id
     }
 
     fn parse_assignment_expression(&mut self) -> ExprId {
@@ -202,6 +285,12 @@ impl<'a> Parser<'a> {
                 return self.ast.assignment_expression(identifier, equals, expr).id;
             }
         }
+        if self.current().kind == TokenKind::Underscore {
+            let underscore = self.consume_and_check(TokenKind::Underscore).clone();
+            let equals = self.consume_and_check(TokenKind::Equals).clone();
+            let expr = self.parse_expr();
+            return self.ast.discard_expression(underscore, equals, expr).id;
+        }
         return self.parse_binary_expression();
     }
 
@@ -210,7 +299,12 @@ impl<'a> Parser<'a> {
         self.parse_binary_expression_recurse(left, 0)
     }
 
-    fn parse_binary_expression_recurse(&mut self, mut left: ExprId, precedence: u8) -> ExprId {
+    fn parse_binary_expression_recurse(&mut self, left: ExprId, precedence: u8) -> ExprId {
+        // right associative operators recurse once per operand
+        self.parse_nested(|parser| parser.parse_binary_operands(left, precedence))
+    }
+
+    fn parse_binary_operands(&mut self, mut left: ExprId, precedence: u8) -> ExprId {
         while let Some(operator) = self.parse_binary_operator() {
             let operator_precedence = operator.precedence();
             if operator_precedence < precedence {
@@ -242,7 +336,7 @@ impl<'a> Parser<'a> {
     fn parse_unary_expression(&mut self) -> ExprId {
         if let Some(operator) = self.parse_unary_operator() {
             self.consume();
-            let operand = self.parse_unary_expression();
+            let operand = self.parse_nested(Self::parse_unary_expression);
             return self.ast.unary_expression(operator, operand).id;
         }
         return self.parse_primary_expression();
@@ -290,9 +384,10 @@ impl<'a> Parser<'a> {
             TokenKind::If => self.parse_if_expression(token),
             TokenKind::Number(number) => self.ast.number_expression(token, number),
             TokenKind::LeftParen => {
+                self.open_delimiter(token.clone());
                 let expr = self.parse_expr();
                 let left_paren = token;
-                let right_paren = self.consume_and_check(TokenKind::RightParen).clone();
+                let right_paren = self.close_delimiter(TokenKind::RightParen);
                 self.ast
                     .parenthesized_expression(left_paren, expr, right_paren)
             }
@@ -318,21 +413,50 @@ impl<'a> Parser<'a> {
 
     fn parse_call_expression(&mut self, callee: ExprId) -> ExprId {
         let left_paren = self.consume_and_check(TokenKind::LeftParen).clone();
+        self.open_delimiter(left_paren.clone());
         let mut arguments = Vec::new();
         while self.current().kind != TokenKind::RightParen && !self.is_at_end() {
             arguments.push(self.parse_expr());
-            if self.current().kind != TokenKind::RightParen {
+            if self.current().kind != TokenKind::RightParen && !self.is_at_end() {
                 self.consume_and_check(TokenKind::Comma);
             }
         }
-        let right_paren = self.consume_and_check(TokenKind::RightParen).clone();
+        let right_paren = self.close_delimiter(TokenKind::RightParen);
         return self
             .ast
             .call_expression(callee, left_paren, arguments, right_paren)
             .id;
     }
 
-    fn peek(&self, offset: isize) -> &Token {
+    fn open_delimiter(&mut self, delimiter: Token) {
+        self.delimiters.push(delimiter);
+    }
+
+    /// Consumes the token closing the innermost open delimiter.
+    ///
+    /// Running into the end of input reports the unclosed delimiter instead of a generic
+    /// unexpected token, and leaves the `Eof` token in place.
+    fn close_delimiter(&mut self, kind: TokenKind) -> Token {
+        let opening = self.delimiters.pop().expect("Closing a delimiter that was never opened");
+        if self.is_at_end() && self.nesting_limit_exceeded {
+            // the closing delimiter was most likely skipped with the rest of the input
+            return self.current().clone();
+        }
+        if self.is_at_end() {
+            let eof = self.current().clone();
+            self.diagnostics_bag
+                .borrow_mut()
+                .report_unclosed_delimiter(&opening, &kind, &eof);
+            return eof;
+        }
+        self.consume_and_check(kind).clone()
+    }
+
+    /// Returns the token `offset` positions away from the current one without consuming anything.
+    ///
+    /// `peek(0)` is the current token. Offsets past the end of the input yield the `Eof` token,
+    /// so lookahead never fails; negative offsets must not reach before the first token.
+    pub fn peek(&self, offset: isize) -> &Token {
         let mut index = (self.current.get_value() as isize + offset) as usize;
         if index >= self.tokens.len() {
             index = self.tokens.len() - 1;
@@ -357,6 +481,13 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Consumes the current token, reporting an unexpected token diagnostic if it is not of `kind`.
+    ///
+    /// The token is consumed either way, so parsing always makes progress.
+    pub fn expect(&self, kind: TokenKind) -> &Token {
+        self.consume_and_check(kind)
+    }
+
     fn consume_and_check(&self, kind: TokenKind) -> &Token {
         let token = self.consume();
         if token.kind != kind {
@@ -367,3 +498,131 @@ impl<'a> Parser<'a> {
         token
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::ast::Ast;
+    use crate::ast::lexer::{Lexer, Token, TokenKind};
+    use crate::ast::parser::Parser;
+    use crate::diagnostics::{DiagnosticsBag, DiagnosticsBagCell};
+
+    fn tokenize(input: &str, diagnostics: &DiagnosticsBagCell) -> Vec<Token> {
+        let mut lexer = Lexer::new(input, Rc::clone(diagnostics));
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    #[test]
+    pub fn should_peek_without_consuming() {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut ast = Ast::new();
+        let parser = Parser::new(tokenize("let a = b", &diagnostics), Rc::clone(&diagnostics), &mut ast);
+
+        assert_eq!(parser.peek(0).kind, TokenKind::Let);
+        assert_eq!(parser.peek(1).kind, TokenKind::Identifier);
+        assert_eq!(parser.peek(0).kind, TokenKind::Let);
+        assert_eq!(parser.peek(10).kind, TokenKind::Eof);
+    }
+
+    #[test]
+    pub fn should_consume_expected_token() {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut ast = Ast::new();
+        let parser = Parser::new(tokenize("let a = b", &diagnostics), Rc::clone(&diagnostics), &mut ast);
+
+        assert_eq!(parser.expect(TokenKind::Let).kind, TokenKind::Let);
+        assert_eq!(parser.peek(0).kind, TokenKind::Identifier);
+        assert!(diagnostics.borrow().diagnostics.is_empty());
+    }
+
+    #[test]
+    pub fn should_report_mismatch_on_expect() {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut ast = Ast::new();
+        let parser = Parser::new(tokenize("let a = b", &diagnostics), Rc::clone(&diagnostics), &mut ast);
+
+        parser.expect(TokenKind::Let);
+        parser.expect(TokenKind::Equals);
+
+        let messages: Vec<String> = diagnostics.borrow().diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+        assert_eq!(messages, vec!["Expected <=>, found <Identifier>"]);
+        assert_eq!(parser.peek(0).kind, TokenKind::Equals);
+    }
+
+    #[test]
+    pub fn should_abort_parsing_input_over_token_limit() {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut ast = Ast::new();
+        let mut parser = Parser::new(tokenize("let a = b + c", &diagnostics), Rc::clone(&diagnostics), &mut ast);
+
+        parser.parse_with_max_tokens(4);
+
+        let messages: Vec<String> = diagnostics.borrow().diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+        assert_eq!(messages, vec!["Input has 6 tokens, exceeding the limit of 4"]);
+        assert_eq!(diagnostics.borrow().diagnostics[0].span.literal, "+");
+        assert!(ast.items.iter().next().is_none());
+    }
+
+    #[test]
+    pub fn should_parse_input_within_token_limit() {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut ast = Ast::new();
+        let mut parser = Parser::new(tokenize("let a = b + c", &diagnostics), Rc::clone(&diagnostics), &mut ast);
+
+        parser.parse_with_max_tokens(6);
+
+        assert!(diagnostics.borrow().diagnostics.is_empty());
+        assert_eq!(ast.items.iter().count(), 1);
+    }
+
+    #[test]
+    pub fn should_skip_unexpected_character_and_keep_lexing() {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let tokens = tokenize("let x @ = 1;", &diagnostics);
+
+        let kinds: Vec<TokenKind> = tokens.iter()
+            .map(|token| token.kind.clone())
+            .filter(|kind| *kind != TokenKind::Whitespace)
+            .collect();
+        assert_eq!(kinds[..4], [TokenKind::Let, TokenKind::Identifier, TokenKind::Equals, TokenKind::Number(1)]);
+        {
+            let diagnostics = diagnostics.borrow();
+            assert_eq!(diagnostics.diagnostics.len(), 1);
+            assert_eq!(diagnostics.diagnostics[0].message, "Unexpected character '@'");
+            assert_eq!((diagnostics.diagnostics[0].span.start, diagnostics.diagnostics[0].span.end), (6, 7));
+        }
+
+        let mut ast = Ast::new();
+        Parser::new(tokens, Rc::clone(&diagnostics), &mut ast).parse();
+
+        assert_eq!(diagnostics.borrow().diagnostics.len(), 1);
+        assert_eq!(ast.items.iter().count(), 1);
+    }
+
+    #[test]
+    pub fn should_lex_prefixed_number_literals() {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let kinds: Vec<TokenKind> = tokenize("0xFF 0b101 0x (7)", &diagnostics).into_iter()
+            .map(|token| token.kind)
+            .filter(|kind| *kind != TokenKind::Whitespace)
+            .collect();
+
+        assert_eq!(kinds, vec![
+            TokenKind::Number(255),
+            TokenKind::Number(5),
+            TokenKind::Number(0),
+            TokenKind::Identifier,
+            TokenKind::LeftParen,
+            TokenKind::Number(7),
+            TokenKind::RightParen,
+            TokenKind::Eof,
+        ]);
+        assert!(diagnostics.borrow().diagnostics.is_empty());
+    }
+}
@@ -1,55 +1,115 @@
+use std::ops::Range;
+
 use crate::ast::*;
 use crate::text::span::TextSpan;
-use termion::color::{self, Fg, Reset};
+use crate::color::Color;
 
 use super::visitor::ASTVisitor;
 
+/// What a piece of printed text is, which decides how it is styled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenCategory {
+    Keyword,
+    Variable,
+    Number,
+    Boolean,
+    Type,
+    /// Operators, punctuation and names that are not variable uses.
+    Text,
+    Error,
+    Whitespace,
+}
+
+impl TokenCategory {
+    fn color(&self) -> Option<Color> {
+        match self {
+            TokenCategory::Keyword => Some(Color::Magenta),
+            TokenCategory::Variable => Some(Color::Green),
+            TokenCategory::Number => Some(Color::Cyan),
+            TokenCategory::Boolean => Some(Color::Yellow),
+            TokenCategory::Type => Some(Color::LightBlue),
+            TokenCategory::Text => Some(Color::LightWhite),
+            TokenCategory::Error => Some(Color::Red),
+            TokenCategory::Whitespace => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledSpan {
+    pub text: String,
+    pub category: TokenCategory,
+}
+
+/// Maps a range of the printed output back to the node it was printed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMapEntry {
+    /// Byte range in [`ASTPrinter::result`].
+    pub output: Range<usize>,
+    pub node: NodeId,
+    /// Where the node is in the original source.
+    pub span: TextSpan,
+}
+
 pub struct ASTPrinter {
     indent: usize,
+    with_color: bool,
+    /// Collects the output as spans when printing for a [`StyledPrinter`].
+    styled_spans: Option<Vec<StyledSpan>>,
     pub result: String,
+    /// Entry for every printed statement and expression, in the order they are finished, when
+    /// enabled with [`ASTPrinter::with_source_map`].
+    pub source_map: Option<Vec<SourceMapEntry>>,
 }
 
 impl ASTPrinter {
-    const NUMBER_COLOR: color::Cyan = color::Cyan;
-    const TEXT_COLOR: color::LightWhite = color::LightWhite;
-    const KEYWORD_COLOR: color::Magenta = color::Magenta;
-    const VARIABLE_COLOR: color::Green = color::Green;
-    const BOOLEAN_COLOR: color::Yellow = color::Yellow;
-    const TYPE_COLOR: color::LightBlue = color::LightBlue;
+    const INDENT_WIDTH: usize = 4;
 
+    fn add(&mut self, category: TokenCategory, text: &str) {
+        if let Some(styled_spans) = &mut self.styled_spans {
+            styled_spans.push(StyledSpan { text: text.to_string(), category });
+        }
+        if let (true, Some(color)) = (self.with_color, category.color()) {
+            self.result.push_str(color.fg_str());
+        }
+        self.result.push_str(text);
+    }
     fn add_whitespace(&mut self) {
-        self.result.push_str(" ")
+        self.add(TokenCategory::Whitespace, " ");
     }
+    /// Only ends the line: the next statement adds the padding of its own indentation level.
     fn add_newline(&mut self) {
-        self.result.push_str(
-            "
-        ",
-        );
+        self.add(TokenCategory::Whitespace, "\n");
     }
     fn add_keyword(&mut self, keyword: &str) {
-        self.result
-            .push_str(&format!("{}{}", Self::KEYWORD_COLOR.fg_str(), keyword,));
+        self.add(TokenCategory::Keyword, keyword);
     }
     fn add_text(&mut self, text: &str) {
-        self.result
-            .push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), text));
+        self.add(TokenCategory::Text, text);
     }
     fn add_variable(&mut self, variable: &str) {
-        self.result
-            .push_str(&format!("{}{}", Self::VARIABLE_COLOR.fg_str(), variable,));
+        self.add(TokenCategory::Variable, variable);
     }
     fn add_padding(&mut self) {
-        for _ in 0..self.indent {
-            self.result.push_str(" ");
+        if self.indent > 0 {
+            self.add(TokenCategory::Whitespace, &" ".repeat(self.indent * Self::INDENT_WIDTH));
         }
     }
     fn add_boolean(&mut self, boolean: bool) {
-        self.result
-            .push_str(&format!("{}{}", Self::BOOLEAN_COLOR.fg_str(), boolean,));
+        self.add(TokenCategory::Boolean, &boolean.to_string());
     }
     fn add_type(&mut self, type_: &str) {
-        self.result
-            .push_str(&format!("{}{}", Self::TYPE_COLOR.fg_str(), type_,));
+        self.add(TokenCategory::Type, type_);
+    }
+    fn add_source_map_entry(&mut self, ast: &Ast, start: usize, node: NodeId) {
+        let end = self.result.len();
+        if let Some(source_map) = &mut self.source_map {
+            let span = match node {
+                NodeId::Stmt(stmt_id) => ast.query_stmt(stmt_id).span(ast),
+                NodeId::Expr(expr_id) => ast.query_expr(expr_id).span(ast),
+            };
+            source_map.push(SourceMapEntry { output: start..end, node, span });
+        }
     }
     fn add_type_annotation(&mut self, type_annotation: &StaticTypeAnnotation) {
         self.add_text(":");
@@ -60,11 +120,52 @@ impl ASTPrinter {
     pub fn new() -> Self {
         Self {
             indent: 0,
+            with_color: true,
+            styled_spans: None,
             result: String::new(),
+            source_map: None,
+        }
+    }
+
+    /// A printer that emits no color escape sequences, for output that is not shown on a terminal.
+    pub fn new_plain() -> Self {
+        Self {
+            with_color: false,
+            ..Self::new()
+        }
+    }
+
+    /// Also records a [`SourceMapEntry`] for every node that is printed.
+    pub fn with_source_map(self) -> Self {
+        Self {
+            source_map: Some(Vec::new()),
+            ..self
         }
     }
 }
 
+/// Prints like [`ASTPrinter`], but produces the output as spans tagged with their
+/// [`TokenCategory`], for renderers that apply their own styling.
+pub struct StyledPrinter {
+    printer: ASTPrinter,
+}
+
+impl StyledPrinter {
+    pub fn new() -> Self {
+        Self {
+            printer: ASTPrinter {
+                styled_spans: Some(Vec::new()),
+                ..ASTPrinter::new_plain()
+            },
+        }
+    }
+
+    pub fn print(mut self, ast: &mut Ast) -> Vec<StyledSpan> {
+        ast.visit(&mut self.printer);
+        self.printer.styled_spans.unwrap_or_default()
+    }
+}
+
 impl ASTVisitor for ASTPrinter {
     fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
         self.add_keyword("func");
@@ -82,13 +183,44 @@ impl ASTVisitor for ASTPrinter {
                 self.add_whitespace();
             }
             self.add_text(&parameter.identifier.span.literal);
-            self.add_type_annotation(&parameter.type_aannotation);
+            self.add_type_annotation(&parameter.type_annotation);
         }
         if !are_parameters_empty {
             self.add_text(")");
             self.add_whitespace();
         }
-        self.visit_expression(ast, decl.body);
+        if let Some(return_type) = &decl.return_type {
+            self.add_text("->");
+            self.add_whitespace();
+            self.add_type(&return_type.type_name.span.literal);
+            self.add_whitespace();
+        }
+        if let Some(body) = decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+    fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {
+        self.add_keyword("extern");
+        self.add_whitespace();
+        self.add_keyword("func");
+        self.add_whitespace();
+        self.add_variable(&extern_statement.identifier.span.literal);
+        self.add_text("(");
+        for (i, parameter) in extern_statement.decl.parameters.iter().enumerate() {
+            if i != 0 {
+                self.add_text(",");
+                self.add_whitespace();
+            }
+            self.add_text(&parameter.identifier.span.literal);
+            self.add_type_annotation(&parameter.type_annotation);
+        }
+        self.add_text(")");
+        if let Some(return_type) = &extern_statement.decl.return_type {
+            self.add_whitespace();
+            self.add_text("->");
+            self.add_whitespace();
+            self.add_type(&return_type.type_name.span.literal);
+        }
     }
     fn visit_return_statement(&mut self, ast: &mut Ast, return_statement: &ReturnStmt) {
         self.add_keyword("return");
@@ -97,13 +229,19 @@ impl ASTVisitor for ASTPrinter {
             self.visit_expression(ast, *expression);
         }
     }
-    fn visit_while_statment(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
         self.add_keyword("while");
         self.add_whitespace();
         self.visit_expression(ast, while_statement.condition);
         self.add_whitespace();
         self.visit_expression(ast, while_statement.body);
     }
+    fn visit_break_statement(&mut self, ast: &mut Ast, break_statement: &BreakStmt) {
+        self.add_keyword("break");
+    }
+    fn visit_continue_statement(&mut self, ast: &mut Ast, continue_statement: &ContinueStmt) {
+        self.add_keyword("continue");
+    }
     fn visit_block_expr(&mut self, ast: &mut Ast, block_statement: &BlockExpr, expr: &Expr) {
         self.add_text("{");
         self.add_newline();
@@ -128,10 +266,32 @@ impl ASTVisitor for ASTPrinter {
             self.visit_expression(ast, else_branch.expr);
         }
     }
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.add_keyword("let");
+        self.add_whitespace();
+        self.add_variable(&let_statement.identifier.span.literal);
+        if let Some(type_annotation) = &let_statement.type_annotation {
+            self.add_type_annotation(type_annotation);
+        }
+        self.add_whitespace();
+        self.add_text("=");
+        self.add_whitespace();
+        self.visit_expression(ast, let_statement.initializer);
+    }
     fn visit_statement(&mut self, ast: &mut Ast, statement: StmtId) {
         self.add_padding();
+        let start = self.result.len();
         self.do_visit_statement(ast, statement);
-        self.result.push_str(&format!("{}\n", Fg(Reset),))
+        self.add_source_map_entry(ast, start, NodeId::Stmt(statement));
+        if self.with_color {
+            self.result.push_str(Color::Reset.fg_str());
+        }
+        self.add_newline();
+    }
+    fn visit_expression(&mut self, ast: &mut Ast, expression: ExprId) {
+        let start = self.result.len();
+        self.do_visit_expression(ast, expression);
+        self.add_source_map_entry(ast, start, NodeId::Expr(expression));
     }
     fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {
         self.add_keyword("rec");
@@ -139,7 +299,7 @@ impl ASTVisitor for ASTPrinter {
     fn visit_call_expression(&mut self, ast: &mut Ast, call_expression: &CallExpr, expr: &Expr) {
         self.visit_expression(ast, call_expression.callee);
         self.add_text("(");
-        for (i, argument) in call_expression.arguements.iter().enumerate() {
+        for (i, argument) in call_expression.arguments.iter().enumerate() {
             if i != 0 {
                 self.add_text(",");
                 self.add_whitespace();
@@ -160,35 +320,33 @@ impl ASTVisitor for ASTPrinter {
         self.add_whitespace();
         self.visit_expression(ast, assignment_expression.expression);
     }
+    fn visit_discard_expression(&mut self, ast: &mut Ast, discard_expression: &DiscardExpr, expr: &Expr) {
+        self.add_text("_");
+        self.add_whitespace();
+        self.add_text("=");
+        self.add_whitespace();
+        self.visit_expression(ast, discard_expression.expression);
+    }
     fn visit_variable_expression(
         &mut self,
         ast: &mut Ast,
         variable_expression: &VarExpr,
         expr: &Expr,
     ) {
-        self.result.push_str(&format!(
-            "{}{}",
-            Self::VARIABLE_COLOR.fg_str(),
-            variable_expression.identifier.span.literal,
-        ));
+        self.add_variable(&variable_expression.identifier.span.literal);
     }
     fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {
-        self.result
-            .push_str(&format!("{}{}", Self::NUMBER_COLOR.fg_str(), number.number,));
+        self.add(TokenCategory::Number, number.literal());
     }
     fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {
         self.add_boolean(boolean.value);
     }
+    /// Error nodes are wrapped in `<error: ...>` so it is visible where the parser recovered.
     fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {
-        self.result
-            .push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), span.literal,));
+        self.add(TokenCategory::Error, &format!("<error: {}>", span.literal));
     }
     fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
-        self.result.push_str(&format!(
-            "{}{}",
-            Self::TEXT_COLOR.fg_str(),
-            unary_expression.operator.token.span.literal,
-        ));
+        self.add_text(&unary_expression.operator.token.span.literal);
         self.visit_expression(ast, unary_expression.operand);
     }
 
@@ -200,11 +358,7 @@ impl ASTVisitor for ASTPrinter {
     ) {
         self.visit_expression(ast, binary_expression.left);
         self.add_whitespace();
-        self.result.push_str(&format!(
-            "{}{}",
-            Self::TEXT_COLOR.fg_str(),
-            binary_expression.operator.token.span.literal,
-        ));
+        self.add_text(&binary_expression.operator.token.span.literal);
         self.add_whitespace();
         self.visit_expression(ast, binary_expression.right);
     }
@@ -214,10 +368,228 @@ impl ASTVisitor for ASTPrinter {
         parenthesized_expression: &ParenthesizedExpr,
         expr: &Expr,
     ) {
-        self.result
-            .push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), "(",));
+        self.add_text("(");
         self.visit_expression(ast, parenthesized_expression.expression);
-        self.result
-            .push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), ")",));
+        self.add_text(")");
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::ast::{Ast, ExprKind, NodeId};
+    use crate::ast::lexer::Lexer;
+    use crate::ast::parser::Parser;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::ast::printer::{ASTPrinter, StyledPrinter, StyledSpan, TokenCategory};
+    use crate::diagnostics::{DiagnosticsBag, DiagnosticsBagCell};
+
+    /// Asserts that `actual_ast` prints the same as the AST parsed from `expected_src`.
+    ///
+    /// Both sides are compared in a canonical form without colors or trailing whitespace, and a
+    /// mismatch panics with a line-by-line diff pointing at the first differing line.
+    pub fn assert_ast_eq(expected_src: &str, actual_ast: &mut Ast) {
+        let mut expected_ast = parse(expected_src);
+        let expected = print_canonical(&mut expected_ast);
+        let actual = print_canonical(actual_ast);
+        if let Some(diff) = diff_lines(&expected, &actual) {
+            panic!("ASTs are not equal\n{}", diff);
+        }
+    }
+
+    pub(crate) fn parse(input: &str) -> Ast {
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut lexer = Lexer::new(input, Rc::clone(&diagnostics));
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            tokens.push(token);
+        }
+        let mut ast = Ast::new();
+        let mut parser = Parser::new(tokens, Rc::clone(&diagnostics), &mut ast);
+        parser.parse();
+        assert!(diagnostics.borrow().diagnostics.is_empty(), "Expected source does not parse: {:?}", diagnostics.borrow().diagnostics);
+        ast
+    }
+
+    fn print_canonical(ast: &mut Ast) -> String {
+        let mut printer = ASTPrinter::new();
+        ast.visit(&mut printer);
+        let plain = strip_colors(&printer.result);
+        plain.lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub(crate) fn strip_colors(text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                // skip a CSI sequence up to and including its final byte
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    fn diff_lines(expected: &str, actual: &str) -> Option<String> {
+        let expected_lines: Vec<&str> = expected.lines().collect();
+        let actual_lines: Vec<&str> = actual.lines().collect();
+        let line_count = expected_lines.len().max(actual_lines.len());
+        let first_difference = (0..line_count)
+            .find(|&i| expected_lines.get(i) != actual_lines.get(i))?;
+        let mut diff = format!("first difference at line {}\n", first_difference + 1);
+        for i in 0..line_count {
+            match (expected_lines.get(i), actual_lines.get(i)) {
+                (Some(expected), Some(actual)) if expected == actual => {
+                    diff.push_str(&format!("  {}\n", expected));
+                }
+                (expected, actual) => {
+                    let marker = if i == first_difference { ">" } else { " " };
+                    if let Some(expected) = expected {
+                        diff.push_str(&format!("{}-{}\n", marker, expected));
+                    }
+                    if let Some(actual) = actual {
+                        diff.push_str(&format!("{}+{}\n", marker, actual));
+                    }
+                }
+            }
+        }
+        Some(diff)
+    }
+
+    #[test]
+    pub fn should_accept_equal_asts() {
+        let mut actual = parse("let a = 1 + b");
+        assert_ast_eq("let a = 1 + b", &mut actual);
+    }
+
+    #[test]
+    pub fn should_show_first_differing_line() {
+        let diff = diff_lines("let a = 1\nlet b = 2\nlet c = 3", "let a = 1\nlet b = 4\nlet c = 5");
+        assert_eq!(
+            diff.as_deref(),
+            Some("first difference at line 2\n  let a = 1\n>-let b = 2\n>+let b = 4\n -let c = 3\n +let c = 5\n"),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "first difference at line 2")]
+    pub fn should_panic_with_diff_on_mismatch() {
+        let mut actual = parse("let a = 1\nlet b = a + 2");
+        assert_ast_eq("let a = 1\nlet b = a + 3", &mut actual);
+    }
+
+    #[test]
+    pub fn should_mark_error_nodes_of_recovered_parse() {
+        let input = "let a = 1 + )\nlet b = 2";
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut lexer = Lexer::new(input, Rc::clone(&diagnostics));
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            tokens.push(token);
+        }
+        let mut ast = Ast::new();
+        Parser::new(tokens, Rc::clone(&diagnostics), &mut ast).parse();
+
+        assert!(!diagnostics.borrow().diagnostics.is_empty());
+        assert_eq!(print_canonical(&mut ast), "let a = 1 + <error: )>\nlet b = 2");
+    }
+
+    #[test]
+    pub fn should_print_bitwise_not_as_parsed() {
+        let mut ast = parse("let a = ~x\nlet b = ~(1 + a)");
+
+        assert_eq!(print_canonical(&mut ast), "let a = ~x\nlet b = ~(1 + a)");
+    }
+
+    #[test]
+    pub fn should_print_number_literals_in_their_original_base() {
+        let mut compilation_unit = CompilationUnit::compile("let a = 0b1010 + 0xFF + 7").expect("Failed to compile");
+
+        assert_eq!(print_canonical(&mut compilation_unit.ast), "let a = 0b1010 + 0xFF + 7");
+    }
+
+    #[test]
+    pub fn should_print_number_literals_as_written() {
+        let mut compilation_unit = CompilationUnit::compile("let a = 0xff + 0x00FF + 007").expect("Failed to compile");
+
+        assert_eq!(print_canonical(&mut compilation_unit.ast), "let a = 0xff + 0x00FF + 007");
+    }
+
+    #[test]
+    #[cfg(not(feature = "color"))]
+    pub fn should_print_plain_text_without_color_feature() {
+        let mut ast = parse("let a = 1 + b");
+        let mut printer = ASTPrinter::new();
+        ast.visit(&mut printer);
+
+        assert!(!printer.result.contains('\x1b'));
+        assert_eq!(printer.result, "let a = 1 + b\n");
+    }
+
+    #[test]
+    pub fn should_print_plain_text_with_indented_blocks() {
+        let input = "\
+        let f = func (a: int) -> int {
+            if a > 0 {
+                return a
+            }
+            return 0
+        }
+        ";
+        let mut ast = parse(input);
+        let mut printer = ASTPrinter::new_plain();
+        ast.visit(&mut printer);
+
+        assert_eq!(
+            printer.result,
+            "let f = func (a: int) -> int {\n    if a > 0 {\n        return a\n    }\n    return 0\n}\n",
+        );
+    }
+
+    #[test]
+    pub fn should_print_styled_spans() {
+        let mut ast = parse("return 1");
+
+        let spans = StyledPrinter::new().print(&mut ast);
+
+        let span = |text: &str, category| StyledSpan { text: text.to_string(), category };
+        assert_eq!(spans, vec![
+            span("return", TokenCategory::Keyword),
+            span(" ", TokenCategory::Whitespace),
+            span("1", TokenCategory::Number),
+            span("\n", TokenCategory::Whitespace),
+        ]);
+    }
+
+    #[test]
+    pub fn should_map_printed_number_back_to_its_node() {
+        let input = "let a = 1 +   22";
+        let mut ast = parse(input);
+        let mut printer = ASTPrinter::new_plain().with_source_map();
+        ast.visit(&mut printer);
+
+        let source_map = printer.source_map.expect("Expected a source map");
+        let entry = source_map.iter()
+            .find(|entry| &printer.result[entry.output.clone()] == "22")
+            .expect("Expected an entry for the number");
+        let expr_id = match entry.node {
+            NodeId::Expr(expr_id) => expr_id,
+            NodeId::Stmt(_) => panic!("Expected an expression"),
+        };
+        assert!(matches!(ast.query_expr(expr_id).kind, ExprKind::Number(ref number) if number.number == 22));
+        assert_eq!(entry.span.start, input.find("22").unwrap());
+        assert_eq!(entry.output.start, printer.result.find("22").unwrap());
     }
 }
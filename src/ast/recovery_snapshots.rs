@@ -0,0 +1,83 @@
+//! Snapshot tests for parser error recovery.
+//!
+//! Every `tests/recovery/*.src` file is lexed and parsed, and the diagnostics are rendered one per
+//! line as `<kind> <line>:<column>: <message>`. The result must match the `.expected` file next
+//! to the source. Run with `UPDATE_SNAPSHOTS=1` to write the current output instead.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::ast::Ast;
+use crate::ast::lexer::Lexer;
+use crate::ast::parser::Parser;
+use crate::diagnostics::{DiagnosticKind, DiagnosticsBag, DiagnosticsBagCell};
+use crate::text::SourceText;
+
+const UPDATE_SNAPSHOTS_VAR: &str = "UPDATE_SNAPSHOTS";
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("recovery")
+}
+
+fn render_diagnostics(input: &str) -> String {
+    let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+    let mut lexer = Lexer::new(input, Rc::clone(&diagnostics));
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token() {
+        tokens.push(token);
+    }
+    let mut ast = Ast::new();
+    Parser::new(tokens, Rc::clone(&diagnostics), &mut ast).parse();
+
+    let text = SourceText::new(input.to_string());
+    let diagnostics = diagnostics.borrow();
+    diagnostics.diagnostics.iter().map(|diagnostic| {
+        let kind = match diagnostic.kind {
+            DiagnosticKind::Error => "error",
+            DiagnosticKind::Warning => "warning",
+        };
+        let line = text.line_index(diagnostic.span.start);
+        let column = diagnostic.span.start - text.line_start(line);
+        format!("{} {}:{}: {}\n", kind, line + 1, column + 1, diagnostic.message)
+    }).collect()
+}
+
+#[test]
+pub fn should_match_recovery_snapshots() {
+    let update = std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_some();
+    let mut sources: Vec<PathBuf> = fs::read_dir(corpus_dir())
+        .expect("Failed to read recovery corpus")
+        .map(|entry| entry.expect("Failed to read corpus entry").path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == "src"))
+        .collect();
+    sources.sort();
+    assert!(!sources.is_empty(), "Recovery corpus is empty");
+
+    let mut mismatches = Vec::new();
+    for source in &sources {
+        let input = fs::read_to_string(source).expect("Failed to read source");
+        let actual = render_diagnostics(&input);
+        let expected_path = source.with_extension("expected");
+        if update {
+            fs::write(&expected_path, &actual).expect("Failed to write snapshot");
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if expected != actual {
+            mismatches.push(format!(
+                "{}\n--- expected\n{}--- actual\n{}",
+                source.display(),
+                expected,
+                actual,
+            ));
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "Recovery snapshots differ, rerun with {}=1 to update:\n{}",
+        UPDATE_SNAPSHOTS_VAR,
+        mismatches.join("\n"),
+    );
+}
@@ -0,0 +1,190 @@
+use crate::ast::{Ast, ExprId, ExprKind, ItemKind, NodeId, StmtId, StmtKind};
+use crate::typings::Type;
+
+impl Ast {
+    /// Returns the variables, parameters and functions visible at byte `offset`, with their types,
+    /// in declaration order.
+    ///
+    /// Top-level functions are visible everywhere, like in the resolver, so they come first. A name
+    /// shadowed by a later declaration is only listed once, with the type of the innermost binding.
+    /// Types are read from the resolved AST, so this is meant to run after resolution.
+    pub fn scope_at(&self, offset: usize) -> Vec<(String, Type)> {
+        let statements: Vec<StmtId> = self.items.iter().map(|item| match &item.kind {
+            ItemKind::Stmt(stmt_id) => *stmt_id,
+        }).collect();
+        let mut scope = Vec::new();
+        self.declare_top_level_functions_in_scope(&statements, &mut scope);
+        self.collect_scope_in_statements(&statements, offset, &mut scope);
+        scope
+    }
+
+    /// Declares the functions the resolver hoists: externs and unannotated `let f = func ...`.
+    fn declare_top_level_functions_in_scope(&self, statements: &[StmtId], scope: &mut Vec<(String, Type)>) {
+        for stmt_id in statements {
+            match &self.query_stmt(*stmt_id).kind {
+                StmtKind::Let(let_stmt) if let_stmt.type_annotation.is_none() => {
+                    let initializer = self.query_expr(let_stmt.initializer);
+                    if let ExprKind::Func(_) = initializer.kind {
+                        Self::declare_in_scope(scope, &let_stmt.identifier.span.literal, initializer.ty.clone());
+                    }
+                }
+                StmtKind::Extern(extern_stmt) => {
+                    Self::declare_in_scope(scope, &extern_stmt.identifier.span.literal, Type::Function(extern_stmt.function_idx));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn collect_scope_in_statements(&self, statements: &[StmtId], offset: usize, scope: &mut Vec<(String, Type)>) {
+        for stmt_id in statements {
+            let (start, end) = self.stmt_extent(*stmt_id);
+            if end <= offset {
+                match &self.query_stmt(*stmt_id).kind {
+                    StmtKind::Let(let_stmt) => {
+                        let ty = match &let_stmt.type_annotation {
+                            Some(type_annotation) => Type::from_annotation(type_annotation).unwrap_or(Type::Error),
+                            None => self.query_expr(let_stmt.initializer).ty.clone(),
+                        };
+                        Self::declare_in_scope(scope, &let_stmt.identifier.span.literal, ty);
+                    }
+                    StmtKind::Extern(extern_stmt) => {
+                        Self::declare_in_scope(scope, &extern_stmt.identifier.span.literal, Type::Function(extern_stmt.function_idx));
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if start <= offset {
+                self.collect_scope_in_children(&self.stmt_children(*stmt_id), offset, scope);
+            }
+            return;
+        }
+    }
+
+    fn collect_scope_in_expr(&self, expr_id: ExprId, offset: usize, scope: &mut Vec<(String, Type)>) {
+        match &self.query_expr(expr_id).kind {
+            ExprKind::Block(block) => {
+                self.collect_scope_in_statements(&block.stmts, offset, scope);
+                return;
+            }
+            ExprKind::Func(func) => {
+                for parameter in &func.decl.parameters {
+                    let ty = Type::from_annotation(&parameter.type_annotation).unwrap_or(Type::Error);
+                    Self::declare_in_scope(scope, &parameter.identifier.span.literal, ty);
+                }
+            }
+            _ => {}
+        }
+        self.collect_scope_in_children(&self.expr_children(expr_id), offset, scope);
+    }
+
+    fn collect_scope_in_children(&self, children: &[NodeId], offset: usize, scope: &mut Vec<(String, Type)>) {
+        let child = children.iter().find(|child| {
+            let (start, end) = match child {
+                NodeId::Expr(expr_id) => self.expr_extent(*expr_id),
+                NodeId::Stmt(stmt_id) => self.stmt_extent(*stmt_id),
+            };
+            start <= offset && offset <= end
+        });
+        match child {
+            Some(NodeId::Expr(expr_id)) => self.collect_scope_in_expr(*expr_id, offset, scope),
+            Some(NodeId::Stmt(stmt_id)) => self.collect_scope_in_statements(&[*stmt_id], offset, scope),
+            None => {}
+        }
+    }
+
+    fn declare_in_scope(scope: &mut Vec<(String, Type)>, name: &str, ty: Type) {
+        scope.retain(|(declared, _)| declared != name);
+        scope.push((name.to_string(), ty));
+    }
+
+    /// Byte range covered by a statement, including the initializer of a let statement.
+    fn stmt_extent(&self, stmt_id: StmtId) -> (usize, usize) {
+        let stmt = self.query_stmt(stmt_id);
+        let span = stmt.span(self);
+        match &stmt.kind {
+            StmtKind::Let(let_stmt) => (span.start, self.expr_extent(let_stmt.initializer).1.max(span.end)),
+            _ => (span.start, span.end),
+        }
+    }
+
+    /// Byte range covered by an expression, including the body of a function expression.
+    fn expr_extent(&self, expr_id: ExprId) -> (usize, usize) {
+        let expr = self.query_expr(expr_id);
+        let span = expr.span(self);
+        match &expr.kind {
+            ExprKind::Func(func) => {
+                let end = func.decl.body.map(|body| self.expr_extent(body).1).unwrap_or(span.end);
+                (span.start, end.max(span.end))
+            }
+            _ => (span.start, span.end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::StmtKind;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::typings::Type;
+
+    #[test]
+    pub fn should_list_inner_and_visible_outer_variables_in_nested_block() {
+        let input = "\
+        let a = 1
+        let f = func (b: int) -> int {
+            let c = b
+            {
+                let d = c
+                let x = true
+                d
+            }
+            let e = 2
+            e
+        }
+        let g = 3
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let offset = input.find("d\n            }").expect("Expected cursor position");
+
+        let f = compilation_unit.ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) if let_stmt.identifier.span.literal == "f" => {
+                Some(compilation_unit.ast.query_expr(let_stmt.initializer).ty.clone())
+            }
+            _ => None,
+        }).expect("Expected a declaration of f");
+
+        let scope = compilation_unit.ast.scope_at(offset);
+
+        assert_eq!(scope, vec![
+            ("f".to_string(), f),
+            ("a".to_string(), Type::Int),
+            ("b".to_string(), Type::Int),
+            ("c".to_string(), Type::Int),
+            ("d".to_string(), Type::Int),
+            ("x".to_string(), Type::Bool),
+        ]);
+    }
+
+    #[test]
+    pub fn should_list_extern_and_later_top_level_functions() {
+        let input = "\
+        let main = func -> int {
+            return later(now())
+        }
+        let later = func (a: int) -> int {
+            return a
+        }
+        extern func now() -> int
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let offset = input.find("now())").expect("Expected cursor position");
+
+        let scope = compilation_unit.ast.scope_at(offset);
+
+        let names: Vec<&str> = scope.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["main", "later", "now"]);
+        assert!(scope.iter().all(|(_, ty)| matches!(ty, Type::Function(_))));
+    }
+}
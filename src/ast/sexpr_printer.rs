@@ -0,0 +1,234 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::*;
+use crate::text::span::TextSpan;
+
+/// Renders the AST as s-expressions, e.g. `(binary + (number 1) (number 2))`.
+///
+/// Every node becomes a parenthesized list starting with its kind, followed by its
+/// own tokens and then its children. Top-level statements are printed one per line.
+pub struct SExprPrinter {
+    pub result: String,
+}
+
+impl SExprPrinter {
+    pub fn new() -> Self {
+        Self { result: String::new() }
+    }
+
+    fn open(&mut self, kind: &str) {
+        self.result.push('(');
+        self.result.push_str(kind);
+    }
+
+    fn atom(&mut self, atom: &str) {
+        self.result.push(' ');
+        self.result.push_str(atom);
+    }
+
+    fn close(&mut self) {
+        self.result.push(')');
+    }
+
+    fn child_expression(&mut self, ast: &mut Ast, expression: ExprId) {
+        self.result.push(' ');
+        self.visit_expression(ast, expression);
+    }
+
+    fn parameters(&mut self, parameters: &[FuncDeclParameter]) {
+        self.result.push(' ');
+        self.open("params");
+        for parameter in parameters {
+            self.result.push(' ');
+            self.open(&parameter.identifier.span.literal);
+            self.atom(&parameter.type_annotation.type_name.span.literal);
+            self.close();
+        }
+        self.close();
+    }
+}
+
+impl ASTVisitor for SExprPrinter {
+    fn visit_item(&mut self, ast: &mut Ast, item: ItemId) {
+        self.visit_item_default(ast, item);
+        self.result.push('\n');
+    }
+
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        self.open("func");
+        self.parameters(&func_expr.decl.parameters);
+        if let Some(return_type) = &func_expr.decl.return_type {
+            self.atom(&return_type.type_name.span.literal);
+        }
+        if let Some(body) = func_expr.decl.body {
+            self.child_expression(ast, body);
+        }
+        self.close();
+    }
+
+    fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {
+        self.open("extern");
+        self.atom(&extern_statement.identifier.span.literal);
+        self.parameters(&extern_statement.decl.parameters);
+        if let Some(return_type) = &extern_statement.decl.return_type {
+            self.atom(&return_type.type_name.span.literal);
+        }
+        self.close();
+    }
+
+    fn visit_return_statement(&mut self, ast: &mut Ast, return_statement: &ReturnStmt) {
+        self.open("return");
+        if let Some(return_value) = return_statement.return_value {
+            self.child_expression(ast, return_value);
+        }
+        self.close();
+    }
+
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
+        self.open("while");
+        self.child_expression(ast, while_statement.condition);
+        self.child_expression(ast, while_statement.body);
+        self.close();
+    }
+
+    fn visit_break_statement(&mut self, ast: &mut Ast, break_statement: &BreakStmt) {
+        self.open("break");
+        self.close();
+    }
+
+    fn visit_continue_statement(&mut self, ast: &mut Ast, continue_statement: &ContinueStmt) {
+        self.open("continue");
+        self.close();
+    }
+
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, expr: &Expr) {
+        self.open("block");
+        for statement in &block_expr.stmts {
+            self.result.push(' ');
+            self.visit_statement(ast, *statement);
+        }
+        self.close();
+    }
+
+    fn visit_if_expression(&mut self, ast: &mut Ast, if_expr: &IfExpr, expr: &Expr) {
+        self.open("if");
+        self.child_expression(ast, if_expr.condition);
+        self.child_expression(ast, if_expr.then_branch);
+        if let Some(else_branch) = &if_expr.else_branch {
+            self.child_expression(ast, else_branch.expr);
+        }
+        self.close();
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.open("let");
+        self.atom(&let_statement.identifier.span.literal);
+        if let Some(type_annotation) = &let_statement.type_annotation {
+            self.atom(&type_annotation.type_name.span.literal);
+        }
+        self.child_expression(ast, let_statement.initializer);
+        self.close();
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {
+        self.open("rec");
+        self.close();
+    }
+
+    fn visit_call_expression(&mut self, ast: &mut Ast, call_expression: &CallExpr, expr: &Expr) {
+        self.open("call");
+        self.child_expression(ast, call_expression.callee);
+        for argument in &call_expression.arguments {
+            self.child_expression(ast, *argument);
+        }
+        self.close();
+    }
+
+    fn visit_assignment_expression(&mut self, ast: &mut Ast, assignment_expression: &AssignExpr, expr: &Expr) {
+        self.open("assign");
+        self.atom(&assignment_expression.identifier.span.literal);
+        self.child_expression(ast, assignment_expression.expression);
+        self.close();
+    }
+
+    fn visit_discard_expression(&mut self, ast: &mut Ast, discard_expression: &DiscardExpr, expr: &Expr) {
+        self.open("discard");
+        self.child_expression(ast, discard_expression.expression);
+        self.close();
+    }
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {
+        self.open("variable");
+        self.atom(&variable_expression.identifier.span.literal);
+        self.close();
+    }
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {
+        self.open("number");
+        self.atom(&number.number.to_string());
+        self.close();
+    }
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {
+        self.open("boolean");
+        self.atom(&boolean.value.to_string());
+        self.close();
+    }
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {
+        self.open("error");
+        self.atom(&span.literal);
+        self.close();
+    }
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.open("unary");
+        self.atom(&unary_expression.operator.token.span.literal);
+        self.child_expression(ast, unary_expression.operand);
+        self.close();
+    }
+
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expression: &BinaryExpr, expr: &Expr) {
+        self.open("binary");
+        self.atom(&binary_expression.operator.token.span.literal);
+        self.child_expression(ast, binary_expression.left);
+        self.child_expression(ast, binary_expression.right);
+        self.close();
+    }
+
+    fn visit_parenthesized_expression(&mut self, ast: &mut Ast, parenthesized_expression: &ParenthesizedExpr, expr: &Expr) {
+        self.open("parenthesized");
+        self.child_expression(ast, parenthesized_expression.expression);
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::sexpr_printer::SExprPrinter;
+    use crate::compilation_unit::CompilationUnit;
+
+    fn print(input: &str) -> String {
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let mut printer = SExprPrinter::new();
+        compilation_unit.ast.visit(&mut printer);
+        printer.result
+    }
+
+    #[test]
+    pub fn should_nest_by_precedence() {
+        assert_eq!(print("1 + 2 * 3"), "(binary + (number 1) (binary * (number 2) (number 3)))\n");
+    }
+
+    #[test]
+    pub fn should_print_statements_one_per_line() {
+        let input = "\
+        let a = 1
+        while a > 0 { a = a - 1 }
+        ";
+
+        assert_eq!(
+            print(input),
+            "(let a (number 1))\n(while (binary > (variable a) (number 0)) (block (assign a (binary - (variable a) (number 1)))))\n",
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::io;
+use std::io::BufRead;
+
+use crate::ast::lexer::{Lexer, TextSpan, Token, TokenKind};
+use crate::diagnostics::DiagnosticsBagCell;
+
+/// Lexes a source read from `reader` without loading all of it into memory.
+///
+/// No token spans a line break, so the source is read and lexed one line at a time. Spans are
+/// offsets from the start of the stream, exactly as if the whole source had been given to
+/// [`Lexer`].
+pub struct StreamLexer<R: BufRead> {
+    reader: R,
+    diagnostics_bag: DiagnosticsBagCell,
+    line: String,
+    pending: VecDeque<Token>,
+    offset: usize,
+    is_finished: bool,
+}
+
+impl<R: BufRead> StreamLexer<R> {
+    pub fn new(reader: R, diagnostics_bag: DiagnosticsBagCell) -> Self {
+        Self {
+            reader,
+            diagnostics_bag,
+            line: String::new(),
+            pending: VecDeque::new(),
+            offset: 0,
+            is_finished: false,
+        }
+    }
+
+    /// Returns the next token like [`Lexer::next_token`], reading more of the source when needed.
+    pub fn next_token(&mut self) -> io::Result<Option<Token>> {
+        while self.pending.is_empty() {
+            if self.is_finished {
+                return Ok(None);
+            }
+            self.lex_line()?;
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    /// Reads the rest of the source and returns all of its tokens.
+    pub fn tokenize(mut self) -> io::Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    fn lex_line(&mut self) -> io::Result<()> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            self.is_finished = true;
+            let eof = TextSpan::new(self.offset, self.offset, String::new());
+            self.pending.push_back(Token::new(TokenKind::Eof, eof));
+            return Ok(());
+        }
+        let mut lexer = Lexer::with_offset(&self.line, self.offset, self.diagnostics_bag.clone());
+        while let Some(token) = lexer.next_token() {
+            if token.kind != TokenKind::Eof {
+                self.pending.push_back(token);
+            }
+        }
+        self.offset += self.line.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use crate::ast::lexer::{Lexer, Token};
+    use crate::ast::parser::Parser;
+    use crate::ast::sexpr_printer::SExprPrinter;
+    use crate::ast::stream_lexer::StreamLexer;
+    use crate::ast::Ast;
+    use crate::diagnostics::{DiagnosticsBag, DiagnosticsBagCell};
+
+    fn print(tokens: Vec<Token>, diagnostics: &DiagnosticsBagCell) -> String {
+        let mut ast = Ast::new();
+        Parser::new(tokens, Rc::clone(diagnostics), &mut ast).parse();
+        let mut printer = SExprPrinter::new();
+        ast.visit(&mut printer);
+        printer.result
+    }
+
+    #[test]
+    pub fn should_lex_stream_like_in_memory_source() {
+        let input = "\
+        let a = 0x10
+        let f = func (b: int) -> int {
+            return a + b
+        }
+        f(2) @ 3
+        ";
+        let in_memory_diagnostics = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut lexer = Lexer::new(input, Rc::clone(&in_memory_diagnostics));
+        let mut in_memory_tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            in_memory_tokens.push(token);
+        }
+        let stream_diagnostics = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let stream_tokens = StreamLexer::new(Cursor::new(input.as_bytes()), Rc::clone(&stream_diagnostics))
+            .tokenize()
+            .expect("Failed to read stream");
+
+        assert_eq!(stream_tokens, in_memory_tokens);
+        assert_eq!(
+            stream_diagnostics.borrow().diagnostics[0].span,
+            in_memory_diagnostics.borrow().diagnostics[0].span,
+        );
+        assert_eq!(print(stream_tokens, &stream_diagnostics), print(in_memory_tokens, &in_memory_diagnostics));
+    }
+}
@@ -1,4 +1,5 @@
-use termion::color::{Fg, Reset};
+use crate::ast::*;
+use crate::text::span::TextSpan;
 
 pub trait ASTVisitor {
     fn visit_item(&mut self, ast: &mut Ast, item: ItemId) {
@@ -19,27 +20,39 @@ pub trait ASTVisitor {
                 self.visit_expression(ast, *expr);
             }
             StmtKind::Let(expr) => {
-                self.visit_let_statment(ast, expr, &statement);
+                self.visit_let_statement(ast, expr, &statement);
             }
             StmtKind::While(stmt) => {
-                self.visit_while_statment(ast, &stmt);
+                self.visit_while_statement(ast, &stmt);
             }
             StmtKind::Return(stmt) => {
-                self.visit_return_statment(ast, &stmt);
+                self.visit_return_statement(ast, &stmt);
+            }
+            StmtKind::Extern(stmt) => {
+                self.visit_extern_statement(ast, &stmt, &statement);
+            }
+            StmtKind::Break(stmt) => {
+                self.visit_break_statement(ast, &stmt);
+            }
+            StmtKind::Continue(stmt) => {
+                self.visit_continue_statement(ast, &stmt);
             }
         }
     }
 
     fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId);
+    fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {}
     fn visit_return_statement(&mut self, ast: &mut Ast, return_statement: &ReturnStmt) {
         if let Some(expr) = &return_statement.return_value {
             self.visit_expression(ast, *expr);
         }
     }
-    fn visit_while_statment(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
         self.visit_expression(ast, while_statement.condition);
         self.visit_expression(ast, while_statement.body);
     }
+    fn visit_break_statement(&mut self, ast: &mut Ast, break_statement: &BreakStmt) {}
+    fn visit_continue_statement(&mut self, ast: &mut Ast, continue_statement: &ContinueStmt) {}
     fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, expr: &Expr) {
         for stmt in &block_expr.stmts {
             self.visit_statement(ast, *stmt);
@@ -56,7 +69,7 @@ pub trait ASTVisitor {
     fn visit_statement(&mut self, ast: &mut Ast, statement: StmtId) {
         self.do_visit_statement(ast, statement);
     }
-    fn do_visit_statement(&mut self, ast: &mut Ast, expression: ExprId) {
+    fn do_visit_expression(&mut self, ast: &mut Ast, expression: ExprId) {
         let expression = ast.query_expr(expression).clone();
         match &expression.kind {
             ExprKind::Number(number) => {
@@ -80,6 +93,9 @@ pub trait ASTVisitor {
             ExprKind::Assignment(expr) => {
                 self.visit_assignment_expression(ast, expr, &expression);
             }
+            ExprKind::Discard(expr) => {
+                self.visit_discard_expression(ast, expr, &expression);
+            }
             ExprKind::Boolean(expr) => {
                 self.visit_boolean_expression(ast, expr, &expression);
             }
@@ -102,7 +118,7 @@ pub trait ASTVisitor {
     }
     fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId);
     fn visit_call_expression(&mut self, ast: &mut Ast, call_expression: &CallExpr, expr: &Expr) {
-        for argument in &call_expression.arguements {
+        for argument in &call_expression.arguments {
             self.visit_expression(ast, *argument);
         }
     }
@@ -117,6 +133,9 @@ pub trait ASTVisitor {
     ) {
         self.visit_expression(ast, assignment_expression.expression);
     }
+    fn visit_discard_expression(&mut self, ast: &mut Ast, discard_expression: &DiscardExpr, expr: &Expr) {
+        self.visit_expression(ast, discard_expression.expression);
+    }
     fn visit_variable_expression(
         &mut self,
         ast: &mut Ast,
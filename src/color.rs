@@ -0,0 +1,37 @@
+/// Terminal colors used by the printers.
+///
+/// Without the `color` feature every escape sequence is empty, so output is plain text and
+/// `termion` is not needed.
+#[derive(Debug, Clone, Copy)]
+pub enum Color {
+    Cyan,
+    LightWhite,
+    Magenta,
+    Green,
+    Yellow,
+    LightBlue,
+    Red,
+    Reset,
+}
+
+impl Color {
+    #[cfg(feature = "color")]
+    pub fn fg_str(&self) -> &'static str {
+        use termion::color;
+        match self {
+            Color::Cyan => color::Cyan.fg_str(),
+            Color::LightWhite => color::LightWhite.fg_str(),
+            Color::Magenta => color::Magenta.fg_str(),
+            Color::Green => color::Green.fg_str(),
+            Color::Yellow => color::Yellow.fg_str(),
+            Color::LightBlue => color::LightBlue.fg_str(),
+            Color::Red => color::Red.fg_str(),
+            Color::Reset => color::Reset.fg_str(),
+        }
+    }
+
+    #[cfg(not(feature = "color"))]
+    pub fn fg_str(&self) -> &'static str {
+        ""
+    }
+}
@@ -1,27 +1,33 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use rs_compiler::{Idx, idx, IdxVec};
 
 use crate::{diagnostics, main, text};
-use crate::ast::{AssignExpr, Ast, BinaryExpr, BinOpKind, BlockExpr, BoolExpr, CallExpr, Expr, ExprId, FuncExpr, FunctionDeclaration, FunctionReturnTypeSyntax, IfExpr, LetStmt, NumberExpr, ParenthesizedExpr, RecExpr, ReturnStmt, Stmt, StmtId, StmtKind, UnaryExpr, UnOpKid, VarExpr, WhileStmt};
-use crate::ast::evaluator::ASTEvaluator;
+use crate::ast::{AssignExpr, Ast, BinaryExpr, BinOpKind, BlockExpr, BoolExpr, CallExpr, DiscardExpr, Expr, ExprId, ExprKind, ExternStmt, FuncExpr, FunctionDeclaration, FunctionReturnTypeSyntax, IfExpr, ItemKind, LetStmt, NumberExpr, ParenthesizedExpr, RecExpr, ReturnStmt, Stmt, StmtId, StmtKind, UnaryExpr, UnOpKid, VarExpr, WhileStmt};
+use crate::ast::evaluator::{ASTEvaluator, RuntimeError, Value};
 use crate::ast::lexer::{Lexer, Token};
 use crate::ast::parser::Parser;
 use crate::ast::visitor::ASTVisitor;
-use crate::diagnostics::DiagnosticsBagCell;
+use crate::diagnostics::{Diagnostic, DiagnosticsBag, DiagnosticsBagCell};
 use crate::diagnostics::printer::DiagnosticsPrinter;
+use crate::passes::control_flow_validator::ControlFlowValidator;
 use crate::text::span::TextSpan;
-use crate::typings::Type;
+use crate::typings::{Type, TypeCheckStrictness};
 
 idx!(FunctionIdx);
 idx!(VariableIdx);
 
 #[derive(Debug, Clone)]
 pub struct Function {
+    pub name: Option<String>,
     pub parameters: Vec<VariableIdx>,
-    pub body: ExprId,
+    /// `None` for extern functions, whose implementation lives outside of the program.
+    pub body: Option<ExprId>,
     pub return_type: Type,
 }
 
@@ -35,6 +41,8 @@ pub struct GlobalScope {
     pub variables: IdxVec<VariableIdx, VariableSymbol>,
     pub functions: IdxVec<FunctionIdx, Function>,
     pub global_variables: Vec<VariableIdx>,
+    /// Globals declared by [`CompilationOptions::predeclared_globals`], whose values the host sets.
+    pub predeclared_globals: Vec<VariableIdx>,
 }
 
 impl GlobalScope {
@@ -43,9 +51,27 @@ impl GlobalScope {
             variables: IdxVec::new(),
             functions: IdxVec::new(),
             global_variables: Vec::new(),
+            predeclared_globals: Vec::new(),
         }
     }
 
+    /// A global scope that already declares `globals`, in order, before any program is resolved.
+    fn with_globals(globals: &[(String, Type)]) -> Self {
+        let mut global_scope = Self::new();
+        for (name, ty) in globals {
+            let variable_idx = global_scope.declare_variable(name, ty.clone(), true);
+            global_scope.predeclared_globals.push(variable_idx);
+        }
+        global_scope
+    }
+
+    /// Returns the predeclared global `name`, even if the program shadows it.
+    pub fn lookup_predeclared_global(&self, name: &str) -> Option<VariableIdx> {
+        self.predeclared_globals.iter()
+            .find(|variable_idx| self.variables.get(**variable_idx).name == name)
+            .copied()
+    }
+
     fn declare_variable(&mut self, identifier: &str, ty: Type, is_global: bool) -> VariableIdx {
         let variable = VariableSymbol {
             name: identifier.to_string(),
@@ -65,10 +91,11 @@ impl GlobalScope {
             .map(|(variable_idx, _)| variable_idx)
     }
 
-    fn create_function(&mut self, function_body_id: &ExprId, parameters: Vec<VariableIdx>, return_type: Type) -> FunctionIdx {
+    fn create_function(&mut self, name: Option<String>, function_body_id: Option<ExprId>, parameters: Vec<VariableIdx>, return_type: Type) -> FunctionIdx {
         let function = Function {
+            name,
             parameters,
-            body: *function_body_id,
+            body: function_body_id,
             return_type,
         };
 
@@ -78,6 +105,12 @@ impl GlobalScope {
     fn set_variable_type(&mut self, variable_idx: VariableIdx, ty: Type) {
         self.variables[variable_idx].ty = ty;
     }
+
+    /// Makes `variable_idx` the latest declaration of its name, as if it had just been declared.
+    fn redeclare_global_variable(&mut self, variable_idx: VariableIdx) {
+        self.global_variables.retain(|global| *global != variable_idx);
+        self.global_variables.push(variable_idx);
+    }
 }
 
 struct LocalScope {
@@ -191,10 +224,18 @@ impl Scopes {
 struct Resolver {
     scopes: Scopes,
     diagnostics: DiagnosticsBagCell,
+    max_locals_per_function: Option<usize>,
+    strictness: TypeCheckStrictness,
+    /// Number of locals, parameters included, allocated by each function being resolved.
+    function_local_counts: Vec<usize>,
+    /// Top-level functions declared ahead of their definition, by their `func` expression.
+    hoisted_functions: HashMap<ExprId, FunctionIdx>,
+    /// Variables of the top-level statements defining a hoisted function.
+    hoisted_variables: HashMap<StmtId, VariableIdx>,
 }
 
-fn expect_type(diagnostics: &DiagnosticsBagCell, expected: Type, actual: &Type, span: &TextSpan) -> Type {
-    if !actual.is_assignable_to(&expected) {
+fn expect_type(diagnostics: &DiagnosticsBagCell, strictness: TypeCheckStrictness, expected: Type, actual: &Type, span: &TextSpan) -> Type {
+    if !actual.is_assignable_with_strictness(&expected, strictness) {
         diagnostics.borrow_mut().report_type_mismatch(span, &expected, actual);
     }
     expected
@@ -205,16 +246,58 @@ impl Resolver {
         Resolver {
             scopes,
             diagnostics,
+            max_locals_per_function: None,
+            strictness: TypeCheckStrictness::default(),
+            function_local_counts: Vec::new(),
+            hoisted_functions: HashMap::new(),
+            hoisted_variables: HashMap::new(),
         }
     }
 
 
     pub fn resolve(&mut self, ast: &mut Ast) {
+        self.declare_top_level_functions(ast);
         for id in ast.items.cloned_indices() {
             self.visit_item(ast, id);
         }
     }
 
+    /// Declares the signatures of all top-level functions, `let f = func ...` and `extern func`,
+    /// before any body is resolved, so a function can call one defined below it.
+    ///
+    /// A `let` with a type annotation is left alone; it is declared in order as usual.
+    fn declare_top_level_functions(&mut self, ast: &Ast) {
+        for item in ast.items.iter() {
+            let stmt = match &item.kind {
+                ItemKind::Stmt(stmt_id) => ast.query_stmt(*stmt_id),
+            };
+            match &stmt.kind {
+                StmtKind::Let(let_stmt) if let_stmt.type_annotation.is_none() => {
+                    if let ExprKind::Func(func_expr) = &ast.query_expr(let_stmt.initializer).kind {
+                        let function_idx = self.declare_function(&func_expr.decl, None);
+                        let variable = self.scopes.declare_variable(&let_stmt.identifier.span.literal, Type::Function(function_idx));
+                        self.hoisted_functions.insert(let_stmt.initializer, function_idx);
+                        self.hoisted_variables.insert(stmt.id, variable);
+                    }
+                }
+                StmtKind::Extern(extern_stmt) => {
+                    let identifier = extern_stmt.identifier.span.literal.clone();
+                    let function_idx = self.declare_function(&extern_stmt.decl, Some(identifier.clone()));
+                    let variable = self.scopes.declare_variable(&identifier, Type::Function(function_idx));
+                    self.hoisted_variables.insert(stmt.id, variable);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the variable hoisted for `stmt_id`, making it the latest declaration of its name.
+    fn take_hoisted_variable(&mut self, stmt_id: StmtId) -> Option<VariableIdx> {
+        let variable = self.hoisted_variables.remove(&stmt_id)?;
+        self.scopes.global_scope.redeclare_global_variable(variable);
+        Some(variable)
+    }
+
     pub fn resolve_binary_expression(
         &self,
         ast: &Ast,
@@ -247,9 +330,44 @@ impl Resolver {
     }
 
     fn expect_type(&self, expected: Type, actual: &Type, span: &TextSpan) -> Type {
-        expect_type(&self.diagnostics, expected, actual, span)
+        expect_type(&self.diagnostics, self.strictness, expected, actual, span)
     }
 
+    fn allocate_local_slot(&mut self, identifier: &Token) {
+        let count = match self.function_local_counts.last_mut() {
+            Some(count) => count,
+            None => return,
+        };
+        *count += 1;
+        if let Some(max_locals) = self.max_locals_per_function {
+            if *count == max_locals + 1 {
+                self.diagnostics.borrow_mut().report_too_many_locals(identifier, max_locals);
+            }
+        }
+    }
+
+
+    fn declare_function(&mut self, decl: &FunctionDeclaration, name: Option<String>) -> FunctionIdx {
+        let parameters = decl.parameters
+            .iter()
+            .map(
+                |parameter| {
+                    let ty = resolve_type_from_string(&self.diagnostics, &parameter.type_annotation.type_name);
+                    self.scopes._declare_variable(
+                        &parameter.identifier.span.literal,
+                        ty,
+                        false,
+                    )
+                }
+            ).collect();
+        let return_type = decl.return_type.as_ref().map(|syntax| resolve_type_from_string(&self.diagnostics, &syntax.type_name)).unwrap_or(Type::Void);
+        self.scopes.global_scope.create_function(
+            name,
+            decl.body,
+            parameters,
+            return_type,
+        )
+    }
 
     pub fn resolve_unary_expression(&self, ast: &Ast, operand: &Expr, operator: &UnOpKid) -> Type {
         let matrix: (Type, Type) = match operator {
@@ -264,57 +382,61 @@ impl Resolver {
 }
 
 fn resolve_type_from_string(diagnostics: &DiagnosticsBagCell, type_name: &Token) -> Type {
-    let ty = Type::from_str(&type_name.span.literal);
-    let ty = match ty {
-        None => {
-            diagnostics.borrow_mut().report_undeclared_type(&type_name);
-            Type::Error
-        }
-        Some(ty) => ty,
-    };
-    ty
+    Type::from_type_name(type_name).unwrap_or_else(|error| {
+        diagnostics.borrow_mut().report_undeclared_type(&error);
+        Type::Error
+    })
 }
 
 
 impl ASTVisitor for Resolver {
     fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
         let decl = &func_expr.decl;
-        let parameters = decl.parameters
-            .iter()
-            .map(
-                |parameter| {
-                    let ty = resolve_type_from_string(&self.diagnostics, &parameter.type_annotation.type_name);
-                    self.scopes._declare_variable(
-                        &parameter.identifier.span.literal,
-                        ty,
-                        false,
-                    )
-                }
-            ).collect();
-        let return_type = decl.return_type.as_ref().map(|syntax| resolve_type_from_string(&self.diagnostics, &syntax.type_name)).unwrap_or(Type::Void);
-        let function_idx = self.scopes.global_scope.create_function(
-            &decl.body,
-            parameters,
-            return_type,
-        );
+        let function_idx = match self.hoisted_functions.get(&expr_id).copied() {
+            Some(function_idx) => function_idx,
+            None => self.declare_function(decl, None),
+        };
         ast.set_type(expr_id, Type::Function(function_idx));
         self.scopes.enter_function_scope(function_idx);
+        self.function_local_counts.push(0);
         let function = self.scopes.global_scope.functions.get(function_idx);
+        ast.set_parameter_variables(expr_id, &function.parameters);
         for parameter in function.parameters.clone() {
             self.scopes.current_local_scope_mut().locals.push(parameter);
         }
-        self.visit_expression(ast, decl.body);
+        for parameter in &decl.parameters {
+            self.allocate_local_slot(&parameter.identifier);
+        }
+        if let Some(body) = decl.body {
+            self.visit_expression(ast, body);
+        }
+        self.function_local_counts.pop();
         self.scopes.exit_function_scope();
     }
 
+    fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {
+        let variable = match self.take_hoisted_variable(stmt.id) {
+            Some(variable) => variable,
+            None => {
+                let identifier = extern_statement.identifier.span.literal.clone();
+                let function_idx = self.declare_function(&extern_statement.decl, Some(identifier.clone()));
+                self.scopes.declare_variable(&identifier, Type::Function(function_idx))
+            }
+        };
+        ast.set_variable_for_stmt(&stmt.id, variable);
+        if let Type::Function(function_idx) = self.scopes.global_scope.variables.get(variable).ty {
+            ast.set_extern_function(stmt.id, function_idx);
+            let function = self.scopes.global_scope.functions.get(function_idx);
+            ast.set_extern_parameter_variables(stmt.id, &function.parameters);
+        }
+    }
+
     fn visit_return_statement(&mut self, ast: &mut Ast, return_statement: &ReturnStmt) {
         let return_keyword = return_statement.return_keyword.clone();
         // todo: do not clone
         match self.scopes.surrounding_function().map(|function| function.clone()) {
-            None => {
-                let mut diagnostics_binding = self.diagnostics.borrow_mut();
-                diagnostics_binding.report_cannot_return_outside_function(&return_statement.return_keyword);
-            }
+            // reported by the control flow validator
+            None => {}
             Some(function) => {
                 if let Some(return_expression) = &return_statement.return_value {
                     self.visit_expression(ast, *return_expression);
@@ -327,7 +449,7 @@ impl ASTVisitor for Resolver {
         }
     }
 
-    fn visit_while_statment(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
         self.visit_expression(ast, while_statement.condition);
         let condition = ast.query_expr(while_statement.condition);
         self.expect_type(Type::Bool, &condition.ty, &condition.span(&ast));
@@ -377,6 +499,11 @@ impl ASTVisitor for Resolver {
         self.visit_expression(ast, let_statement.initializer);
         let initializer_expression = ast.query_expr(let_statement.initializer);
         let ty = match &let_statement.type_annotation {
+            // a discard or a void call has no value the variable could hold at runtime
+            _ if initializer_expression.ty == Type::Void => {
+                self.diagnostics.borrow_mut().report_void_initializer(&let_statement.identifier, &initializer_expression.span(&ast));
+                Type::Error
+            }
             Some(type_annotation) => {
                 let ty = resolve_type_from_string(&self.diagnostics, &type_annotation.type_name);
                 self.expect_type(ty.clone(), &initializer_expression.ty, &initializer_expression.span(&ast));
@@ -386,7 +513,11 @@ impl ASTVisitor for Resolver {
                 initializer_expression.ty.clone()
             }
         };
-        let variable = self.scopes.declare_variable(&identifier, ty);
+        let variable = match self.take_hoisted_variable(stmt.id) {
+            Some(variable) => variable,
+            None => self.scopes.declare_variable(&identifier, ty),
+        };
+        self.allocate_local_slot(&let_statement.identifier);
         ast.set_variable_for_stmt(&stmt.id, variable);
     }
 
@@ -465,6 +596,11 @@ impl ASTVisitor for Resolver {
         ast.set_type(expr.id, ty);
     }
 
+    fn visit_discard_expression(&mut self, ast: &mut Ast, discard_expression: &DiscardExpr, expr: &Expr) {
+        self.visit_expression(ast, discard_expression.expression);
+        ast.set_type(expr.id, Type::Void);
+    }
+
     fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {
         let variable_name = &variable_expression.identifier.span.literal;
         match self.scopes.lookup_variable(variable_name) {
@@ -518,39 +654,365 @@ impl ASTVisitor for Resolver {
     }
 }
 
+/// A program that has been parsed, resolved and type checked.
+pub type CompiledProgram = CompilationUnit;
+
+/// Compiles `source` up to type checking with the default options.
+///
+/// Unlike [`CompilationUnit::compile`], nothing is printed: all diagnostics are returned to the caller.
+pub fn compile(source: &str) -> Result<CompiledProgram, Vec<Diagnostic>> {
+    compile_silently_with_options(source, &CompilationOptions::default())
+}
+
+/// Like [`compile`], with the given options.
+///
+/// Unlike [`CompilationUnit::compile_with_options`], nothing is printed.
+pub fn compile_silently_with_options(source: &str, options: &CompilationOptions) -> Result<CompiledProgram, Vec<Diagnostic>> {
+    let compile = || CompilationUnit::compile_silently(source, options)
+        .map_err(|diagnostics_bag| diagnostics_bag.borrow().diagnostics.clone());
+    if options.catch_internal_errors {
+        catch_internal_errors(compile)
+    } else {
+        compile()
+    }
+}
+
+/// Token limit of [`parse_bytes`].
+const UNTRUSTED_MAX_TOKENS: usize = 1 << 16;
+/// Nesting limit of [`parse_bytes`], low enough to stay well within the default stack size.
+const UNTRUSTED_MAX_NESTING_DEPTH: usize = 64;
+
+/// Parses arbitrary bytes, such as fuzzer input, without ever panicking.
+///
+/// Invalid UTF-8 is replaced with `U+FFFD` and lexed like any other unexpected character. The
+/// input is limited to [`UNTRUSTED_MAX_TOKENS`] tokens and [`UNTRUSTED_MAX_NESTING_DEPTH`]
+/// nested expressions, so parsing takes bounded time and stack. A panic inside the parser is
+/// still returned as an internal compiler error diagnostic.
+pub fn parse_bytes(bytes: &[u8]) -> Result<Ast, Vec<Diagnostic>> {
+    let source = String::from_utf8_lossy(bytes);
+    let options = CompilationOptions {
+        max_tokens: Some(UNTRUSTED_MAX_TOKENS),
+        max_nesting_depth: Some(UNTRUSTED_MAX_NESTING_DEPTH),
+        ..Default::default()
+    };
+    catch_internal_errors(|| {
+        let diagnostics_bag: DiagnosticsBagCell = Rc::new(RefCell::new(diagnostics::DiagnosticsBag::new()));
+        let tokens = CompilationUnit::lex(&source, &diagnostics_bag, &mut None);
+        CompilationUnit::parse(tokens, &options, &diagnostics_bag, &mut None)
+            .map_err(|diagnostics_bag| diagnostics_bag.borrow().diagnostics.clone())
+    })
+}
+
+/// Runs `compile`, turning a panic into an internal compiler error diagnostic.
+fn catch_internal_errors<T>(compile: impl FnOnce() -> Result<T, Vec<Diagnostic>>) -> Result<T, Vec<Diagnostic>> {
+    panic::catch_unwind(AssertUnwindSafe(compile)).unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let mut diagnostics_bag = DiagnosticsBag::new();
+        diagnostics_bag.report_internal_compiler_error(&message);
+        Err(diagnostics_bag.diagnostics)
+    })
+}
+
+/// Type checks copies of `before` and `after` and compares the types of their top-level
+/// expressions, for asserting that a transform did not change what the program computes.
+pub fn same_types(before: &Ast, after: &Ast) -> bool {
+    let before_types = top_level_types(before);
+    let after_types = top_level_types(after);
+    before_types == after_types
+}
+
+fn top_level_types(ast: &Ast) -> Vec<Type> {
+    let mut ast = ast.clone();
+    let diagnostics_bag: DiagnosticsBagCell = Rc::new(RefCell::new(diagnostics::DiagnosticsBag::new()));
+    let mut resolver = Resolver::new(diagnostics_bag, Scopes::new());
+    resolver.resolve(&mut ast);
+    ast.items.iter().filter_map(|item| match &item.kind {
+        ItemKind::Stmt(stmt_id) => match &ast.query_stmt(*stmt_id).kind {
+            StmtKind::Expr(expr_id) => Some(*expr_id),
+            StmtKind::Let(let_stmt) => Some(let_stmt.initializer),
+            _ => None,
+        },
+    }).map(|expr_id| ast.query_expr(expr_id).ty.clone()).collect()
+}
+
+/// The last phase [`CompilationUnit::compile_until`] runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopAfter {
+    Lex,
+    Parse,
+    /// Name resolution and type checking run as a single pass, so this stops at the same point
+    /// as `Typecheck`.
+    Resolve,
+    Typecheck,
+}
+
+impl StopAfter {
+    pub fn from_str(s: &str) -> Option<StopAfter> {
+        match s {
+            "lex" => Some(StopAfter::Lex),
+            "parse" => Some(StopAfter::Parse),
+            "resolve" => Some(StopAfter::Resolve),
+            "typecheck" => Some(StopAfter::Typecheck),
+            _ => None,
+        }
+    }
+}
+
+pub enum PhaseOutput {
+    Tokens(Vec<Token>),
+    Ast(Ast),
+    Program(CompilationUnit),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompilationOptions {
+    /// Name of the function execution starts in.
+    ///
+    /// When `None`, `main` is used if the program declares it, otherwise the top-level statements are run.
+    pub entry_point: Option<String>,
+    /// Maximum number of locals, parameters included, a single function may declare.
+    ///
+    /// `None` means unlimited.
+    pub max_locals_per_function: Option<usize>,
+    /// Whether implicit conversions, such as `int` to `float`, are allowed in assignments.
+    pub strictness: TypeCheckStrictness,
+    /// Maximum number of tokens the input may consist of before parsing is aborted.
+    ///
+    /// `None` means unlimited.
+    pub max_tokens: Option<usize>,
+    /// Maximum number of expressions that may be nested inside each other.
+    ///
+    /// `None` means unlimited.
+    pub max_nesting_depth: Option<usize>,
+    /// Whether to record [`PhaseStats`] for the compiled program.
+    pub collect_stats: bool,
+    /// Whether [`compile_silently_with_options`] reports a panic inside the compiler as a diagnostic
+    /// instead of unwinding into the caller.
+    ///
+    /// Off by default, so that compiler bugs are not masked in tests.
+    pub catch_internal_errors: bool,
+    /// Global variables the host declares for every program, with their types.
+    ///
+    /// Programs can read and assign them like their own globals, shadowing included. Only their
+    /// types are known to the compiler, the host provides their values.
+    pub predeclared_globals: Vec<(String, Type)>,
+}
+
+/// How long a single compilation phase took.
+#[derive(Debug, Clone)]
+pub struct PhaseStat {
+    pub phase: &'static str,
+    pub duration: Duration,
+    /// Number of tokens produced by the lexer, or of AST nodes handled by the later phases.
+    pub processed: usize,
+}
+
+/// Timings of the compilation phases, in the order they ran.
+#[derive(Debug, Clone, Default)]
+pub struct PhaseStats {
+    pub phases: Vec<PhaseStat>,
+}
+
+impl PhaseStats {
+    fn for_options(options: &CompilationOptions) -> Option<PhaseStats> {
+        options.collect_stats.then(PhaseStats::default)
+    }
+
+    /// Runs `phase`, recording its duration and `processed(&output)` if stats are collected.
+    ///
+    /// When `stats` is `None` this only calls `run`, so disabled stats cost nothing.
+    fn measure<T>(stats: &mut Option<PhaseStats>, phase: &'static str, run: impl FnOnce() -> T, processed: impl FnOnce(&T) -> usize) -> T {
+        let stats = match stats {
+            Some(stats) => stats,
+            None => return run(),
+        };
+        let started = Instant::now();
+        let output = run();
+        let duration = started.elapsed();
+        stats.phases.push(PhaseStat { phase, duration, processed: processed(&output) });
+        output
+    }
+}
+
+impl Display for PhaseStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<10} {:>14} {:>10}", "phase", "duration", "processed")?;
+        for stat in &self.phases {
+            writeln!(f, "{:<10} {:>14} {:>10}", stat.phase, format!("{:?}", stat.duration), stat.processed)?;
+        }
+        Ok(())
+    }
+}
+
 pub struct CompilationUnit {
     pub ast: Ast,
     pub diagnostics_bag: DiagnosticsBagCell,
     pub global_scope: GlobalScope,
+    pub entry_point: Option<FunctionIdx>,
+    /// Present when compiled with [`CompilationOptions::collect_stats`].
+    pub stats: Option<PhaseStats>,
 }
 
 impl CompilationUnit {
     pub fn compile(input: &str) -> Result<CompilationUnit, DiagnosticsBagCell> {
+        Self::compile_with_options(input, &CompilationOptions::default())
+    }
+
+    pub fn compile_with_options(input: &str, options: &CompilationOptions) -> Result<CompilationUnit, DiagnosticsBagCell> {
+        let text = text::SourceText::new(input.to_string());
+        let mut compilation_unit = Self::compile_silently(input, options).map_err(|diagnostics_bag| {
+            Self::print_diagnostics(&text, &diagnostics_bag);
+            diagnostics_bag
+        })?;
+        compilation_unit.ast.visualize();
+        Ok(compilation_unit)
+    }
+
+    /// Runs the phases up to and including `stop_after`, returning that phase's output.
+    ///
+    /// Diagnostics are printed like in [`CompilationUnit::compile`].
+    pub fn compile_until(input: &str, options: &CompilationOptions, stop_after: StopAfter) -> Result<PhaseOutput, DiagnosticsBagCell> {
         let text = text::SourceText::new(input.to_string());
-        let mut lexer = Lexer::new(input);
-        let mut tokens = Vec::new();
-        while let Some(token) = lexer.next_token() {
-            tokens.push(token);
+        let diagnostics_bag: DiagnosticsBagCell = Rc::new(RefCell::new(diagnostics::DiagnosticsBag::new()));
+        let print_diagnostics = |diagnostics_bag: DiagnosticsBagCell| {
+            Self::print_diagnostics(&text, &diagnostics_bag);
+            diagnostics_bag
+        };
+        let mut stats = PhaseStats::for_options(options);
+        let tokens = Self::lex(input, &diagnostics_bag, &mut stats);
+        if stop_after == StopAfter::Lex {
+            Self::check_diagnostics(&diagnostics_bag).map_err(print_diagnostics)?;
+            return Ok(PhaseOutput::Tokens(tokens));
+        }
+        let ast = Self::parse(tokens, options, &diagnostics_bag, &mut stats).map_err(print_diagnostics)?;
+        if stop_after == StopAfter::Parse {
+            return Ok(PhaseOutput::Ast(ast));
         }
+        let mut compilation_unit = Self::check(ast, options, diagnostics_bag, &mut stats).map_err(print_diagnostics)?;
+        compilation_unit.stats = stats;
+        Ok(PhaseOutput::Program(compilation_unit))
+    }
+
+    /// Runs all passes up to type checking without printing anything.
+    fn compile_silently(input: &str, options: &CompilationOptions) -> Result<CompilationUnit, DiagnosticsBagCell> {
         let diagnostics_bag: DiagnosticsBagCell = Rc::new(RefCell::new(diagnostics::DiagnosticsBag::new()));
-        let mut ast = Ast::new();
-        let mut parser = Parser::new(
-            tokens,
-            Rc::clone(&diagnostics_bag),
-            &mut ast,
-        );
-        parser.parse();
-        ast.visualize();
-        Self::check_diagnostics(&text, &diagnostics_bag).map_err(|_| Rc::clone(&diagnostics_bag))?;
-        let global_scope = GlobalScope::new();
+        let mut stats = PhaseStats::for_options(options);
+        let tokens = Self::lex(input, &diagnostics_bag, &mut stats);
+        let ast = Self::parse(tokens, options, &diagnostics_bag, &mut stats)?;
+        let mut compilation_unit = Self::check(ast, options, diagnostics_bag, &mut stats)?;
+        compilation_unit.stats = stats;
+        Ok(compilation_unit)
+    }
+
+    fn lex(input: &str, diagnostics_bag: &DiagnosticsBagCell, stats: &mut Option<PhaseStats>) -> Vec<Token> {
+        PhaseStats::measure(stats, "lex", || {
+            let mut lexer = Lexer::new(input, Rc::clone(diagnostics_bag));
+            let mut tokens = Vec::new();
+            while let Some(token) = lexer.next_token() {
+                tokens.push(token);
+            }
+            tokens
+        }, |tokens| tokens.len())
+    }
+
+    fn parse(tokens: Vec<Token>, options: &CompilationOptions, diagnostics_bag: &DiagnosticsBagCell, stats: &mut Option<PhaseStats>) -> Result<Ast, DiagnosticsBagCell> {
+        let ast = PhaseStats::measure(stats, "parse", || {
+            let mut ast = Ast::new();
+            let mut parser = Parser::new(
+                tokens,
+                Rc::clone(diagnostics_bag),
+                &mut ast,
+            );
+            parser.max_nesting_depth = options.max_nesting_depth;
+            match options.max_tokens {
+                Some(max_tokens) => parser.parse_with_max_tokens(max_tokens),
+                None => parser.parse(),
+            }
+            ast
+        }, Ast::node_count);
+        Self::check_diagnostics(diagnostics_bag)?;
+        Ok(ast)
+    }
+
+    /// Validates, resolves and type checks a parsed program.
+    fn check(mut ast: Ast, options: &CompilationOptions, diagnostics_bag: DiagnosticsBagCell, stats: &mut Option<PhaseStats>) -> Result<CompilationUnit, DiagnosticsBagCell> {
+        let node_count = ast.node_count();
+        let mut control_flow_validator = ControlFlowValidator::new(Rc::clone(&diagnostics_bag));
+        PhaseStats::measure(stats, "validate", || control_flow_validator.validate(&mut ast), |_| node_count);
+        let global_scope = GlobalScope::with_globals(&options.predeclared_globals);
         let scopes = Scopes::from_global_scope(global_scope);
         let mut resolver = Resolver::new(Rc::clone(&diagnostics_bag), scopes);
-        resolver.resolve(&mut ast);
-        Self::check_diagnostics(&text, &diagnostics_bag).map_err(|_| Rc::clone(&diagnostics_bag))?;
+        resolver.max_locals_per_function = options.max_locals_per_function;
+        resolver.strictness = options.strictness;
+        // name resolution and type checking happen in the same pass
+        PhaseStats::measure(stats, "resolve", || resolver.resolve(&mut ast), |_| node_count);
+        let global_scope = resolver.scopes.global_scope;
+        let entry_point = Self::find_entry_point(&ast, &global_scope, options, &diagnostics_bag);
+        Self::check_diagnostics(&diagnostics_bag)?;
         Ok(CompilationUnit {
-            global_scope: resolver.scopes.global_scope,
+            global_scope,
             ast,
             diagnostics_bag,
+            entry_point,
+            stats: None,
+        })
+    }
+
+    fn find_entry_point(ast: &Ast, global_scope: &GlobalScope, options: &CompilationOptions, diagnostics_bag: &DiagnosticsBagCell) -> Option<FunctionIdx> {
+        // without a configured entry point `main` is optional, but checked the same way when present
+        let (name, is_required) = match &options.entry_point {
+            Some(name) => (name.as_str(), true),
+            None => ("main", false),
+        };
+        // globals shadowed by a function of the same name are not entry points, and predeclared
+        // globals have no body to run
+        let candidates: Vec<(VariableIdx, &Token)> = global_scope.global_variables.iter()
+            .filter(|variable_idx| {
+                let variable = global_scope.variables.get(**variable_idx);
+                variable.name == name && matches!(variable.ty, Type::Function(_))
+            })
+            .filter_map(|variable_idx| Some((*variable_idx, Self::find_declaration(ast, *variable_idx)?)))
+            .collect();
+        let (variable_idx, declaration) = match candidates.as_slice() {
+            [] => {
+                if is_required {
+                    diagnostics_bag.borrow_mut().report_no_entry_function_found(name);
+                }
+                return None;
+            }
+            [candidate] => *candidate,
+            [_, duplicates @ ..] => {
+                for (_, duplicate) in duplicates {
+                    diagnostics_bag.borrow_mut().report_multiple_entry_functions(duplicate);
+                }
+                return None;
+            }
+        };
+        let function_idx = match global_scope.variables.get(variable_idx).ty {
+            Type::Function(function_idx) => Some(function_idx),
+            _ => None,
+        };
+        let is_valid = function_idx.map(|function_idx| global_scope.functions.get(function_idx))
+            .map_or(false, |function| {
+                function.body.is_some()
+                    && function.parameters.is_empty()
+                    && matches!(function.return_type, Type::Int | Type::Void)
+            });
+        if !is_valid {
+            diagnostics_bag.borrow_mut().report_invalid_entry_point_signature(declaration);
+            return None;
+        }
+        function_idx
+    }
+
+    /// Returns the identifier declaring the global `variable_idx`, or `None` for predeclared globals.
+    fn find_declaration(ast: &Ast, variable_idx: VariableIdx) -> Option<&Token> {
+        ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) if let_stmt.variable_idx == variable_idx => Some(&let_stmt.identifier),
+            StmtKind::Extern(extern_stmt) if extern_stmt.variable_idx == variable_idx => Some(&extern_stmt.identifier),
+            _ => None,
         })
     }
 
@@ -563,35 +1025,522 @@ impl CompilationUnit {
     }
 
     pub fn run(&mut self) {
+        match self.evaluate() {
+            Ok(result) => println!("Result: {:?}", result),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+
+    pub fn evaluate(&mut self) -> Result<Option<Value>, RuntimeError> {
         let mut eval = ASTEvaluator::new(
             &self.global_scope,
         );
-        let main_function_ref = self.global_scope.lookup_global_variable("main");
-        if let Some(function) = main_function_ref {
-            let function = self.global_scope.variables.get(function);
-            let function = match &function.ty {
-                Type::Function(function) => function,
-                _ => panic!("Expected function type"),
-            };
-            let function = self.global_scope.functions.get(*function);
-            eval.visit_expression(&mut self.ast, function.body);
-        } else {
-            self.ast.visit(&mut eval);
+        // the top-level statements initialize the globals the entry point may read
+        let result = eval.evaluate(&mut self.ast);
+        match self.entry_point {
+            Some(function) => {
+                result?;
+                let function = self.global_scope.functions.get(function);
+                let body = function.body.expect("Entry point must not be an extern function");
+                eval.evaluate_expression(&mut self.ast, body)
+            }
+            None => result,
         }
-        println!("Result: {:?}", eval.last_value);
     }
 
 
-    fn check_diagnostics(text: &text::SourceText, diagnostics_bag: &DiagnosticsBagCell) -> Result<(), ()> {
-        let diagnostics_binding = diagnostics_bag.borrow();
-        if diagnostics_binding.diagnostics.len() > 0 {
-            let diagnostics_printer = DiagnosticsPrinter::new(
-                &text,
-                &diagnostics_binding.diagnostics,
-            );
-            diagnostics_printer.print();
-            return Err(());
+    fn check_diagnostics(diagnostics_bag: &DiagnosticsBagCell) -> Result<(), DiagnosticsBagCell> {
+        if diagnostics_bag.borrow().diagnostics.len() > 0 {
+            return Err(Rc::clone(diagnostics_bag));
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn print_diagnostics(text: &text::SourceText, diagnostics_bag: &DiagnosticsBagCell) {
+        let diagnostics_binding = diagnostics_bag.borrow();
+        let diagnostics_printer = DiagnosticsPrinter::new(
+            &text,
+            &diagnostics_binding.diagnostics,
+        );
+        diagnostics_printer.print();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::evaluator::Value;
+    use crate::ast::{ExprKind, NumberBase, NumberExpr, StmtKind};
+    use crate::ast::lexer::{self, Token, TokenKind};
+    use crate::compilation_unit::{catch_internal_errors, compile, compile_silently_with_options, parse_bytes, same_types, CompilationOptions, CompilationUnit, PhaseOutput, StopAfter};
+    use crate::passes::constant_folder::ConstantFolder;
+    use crate::typings::{Type, TypeCheckStrictness};
+    use std::time::Duration;
+
+    fn compile_with_entry_point(input: &str, entry_point: &str) -> Result<CompilationUnit, Vec<String>> {
+        let options = CompilationOptions { entry_point: Some(entry_point.to_string()), ..Default::default() };
+        CompilationUnit::compile_with_options(input, &options).map_err(|diagnostics| {
+            diagnostics.borrow().diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect()
+        })
+    }
+
+    #[test]
+    pub fn should_run_custom_entry_point() {
+        let input = "\
+        let helper = func -> int {
+            return 1
+        }
+        let start = func -> int {
+            return 42
+        }
+        ";
+
+        let mut compilation_unit = compile_with_entry_point(input, "start").expect("Failed to compile");
+
+        assert!(compilation_unit.entry_point.is_some());
+        assert_eq!(compilation_unit.evaluate(), Ok(Some(Value::Number(42))));
+    }
+
+    #[test]
+    pub fn should_initialize_globals_before_running_entry_point() {
+        let input = "\
+        let x = 5
+        let main = func -> int {
+            return x
+        }
+        ";
+
+        let mut compilation_unit = compile(input).expect("Failed to compile");
+
+        assert!(compilation_unit.entry_point.is_some());
+        assert_eq!(compilation_unit.evaluate(), Ok(Some(Value::Number(5))));
+    }
+
+    #[test]
+    pub fn should_accept_single_entry_function() {
+        let input = "\
+        let start = func {}
+        ";
+
+        let compilation_unit = compile_with_entry_point(input, "start").expect("Failed to compile");
+
+        assert!(compilation_unit.entry_point.is_some());
+    }
+
+    #[test]
+    pub fn should_report_multiple_entry_functions() {
+        let input = "\
+        let start = func {}
+        let start = func -> int {
+            return 1
+        }
+        ";
+
+        let diagnostics = compile_with_entry_point(input, "start").err().expect("Expected diagnostics");
+
+        assert_eq!(diagnostics, vec!["Multiple entry functions 'start' found"]);
+    }
+
+    #[test]
+    pub fn should_ignore_non_function_global_shadowed_by_entry_function() {
+        let input = "\
+        let start = 1
+        let start = func {}
+        ";
+
+        let compilation_unit = compile_with_entry_point(input, "start").expect("Failed to compile");
+
+        assert!(compilation_unit.entry_point.is_some());
+    }
+
+    #[test]
+    pub fn should_report_missing_entry_point() {
+        let input = "\
+        let main = func {}
+        ";
+
+        let diagnostics = compile_with_entry_point(input, "start").err().expect("Expected diagnostics");
+
+        assert_eq!(diagnostics, vec!["No entry function 'start' found"]);
+    }
+
+    #[test]
+    pub fn should_report_entry_point_with_wrong_signature() {
+        let input = "\
+        let start = func (a: int) -> int {
+            return a
+        }
+        ";
+
+        let diagnostics = compile_with_entry_point(input, "start").err().expect("Expected diagnostics");
+
+        assert_eq!(diagnostics, vec!["Entry point 'start' must be of type '() -> int' or '() -> void'"]);
+    }
+
+    #[test]
+    pub fn should_check_signature_of_default_entry_point() {
+        let with_parameter = "\
+        let main = func (a: int) -> int {
+            return a
+        }
+        ";
+        let external = "\
+        extern func main() -> int
+        ";
+
+        for input in [with_parameter, external] {
+            let diagnostics = compile(input).err().expect("Expected diagnostics");
+
+            assert_eq!(diagnostics[0].message, "Entry point 'main' must be of type '() -> int' or '() -> void'");
+        }
+    }
+
+    #[test]
+    pub fn should_run_top_level_statements_without_main() {
+        let mut compilation_unit = compile("let a = 1
+a + 1").expect("Failed to compile");
+
+        assert!(compilation_unit.entry_point.is_none());
+        assert_eq!(compilation_unit.evaluate(), Ok(Some(Value::Number(2))));
+    }
+
+    #[test]
+    pub fn should_return_compiled_program_for_clean_source() {
+        let input = "\
+        let a = 1
+        let b = a + 2
+        ";
+
+        let program = compile(input).expect("Expected program to compile");
+
+        assert!(program.diagnostics_bag.borrow().diagnostics.is_empty());
+    }
+
+    #[test]
+    pub fn should_return_all_diagnostics_for_broken_source() {
+        let input = "\
+        let a: bool = 1
+        let b = c
+        ";
+
+        let diagnostics = compile(input).err().expect("Expected diagnostics");
+        let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+
+        assert_eq!(messages, vec!["Expected type 'bool', found 'int'", "Undeclared variable 'c'"]);
+    }
+
+    fn compile_with_max_locals(input: &str, max_locals: usize) -> Result<CompilationUnit, Vec<String>> {
+        let options = CompilationOptions { max_locals_per_function: Some(max_locals), ..Default::default() };
+        CompilationUnit::compile_with_options(input, &options).map_err(|diagnostics| {
+            diagnostics.borrow().diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect()
+        })
+    }
+
+    #[test]
+    pub fn should_report_function_exceeding_local_cap() {
+        let input = "\
+        let f = func (a: int) -> int {
+            let b = a
+            let c = b
+            return c
+        }
+        ";
+
+        let diagnostics = compile_with_max_locals(input, 2).err().expect("Expected diagnostics");
+
+        assert_eq!(diagnostics, vec!["Function declares more than 2 locals"]);
+    }
+
+    #[test]
+    pub fn should_accept_function_within_local_cap() {
+        let input = "\
+        let x = 1
+        let y = 2
+        let z = 3
+        let f = func (a: int) -> int {
+            let b = a
+            return b
+        }
+        ";
+
+        assert!(compile_with_max_locals(input, 2).is_ok());
+    }
+
+    #[test]
+    pub fn should_preserve_types_when_folding_constants() {
+        let input = "\
+        let a = 1 + 2
+        let b = a == 3
+        ";
+        let mut compilation_unit = compile(input).expect("Failed to compile");
+        let before = compilation_unit.ast.clone();
+
+        ConstantFolder::new().fold(&mut compilation_unit.ast);
+
+        assert!(same_types(&before, &compilation_unit.ast));
+    }
+
+    #[test]
+    pub fn should_detect_transform_changing_types() {
+        let input = "\
+        let a = 1 == 2
+        ";
+        let mut compilation_unit = compile(input).expect("Failed to compile");
+        let before = compilation_unit.ast.clone();
+        let initializer = compilation_unit.ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) => Some(let_stmt.initializer),
+            _ => None,
+        }).expect("Expected a let statement");
+
+        // a broken "fold" that turns the comparison into a number
+        let span = compilation_unit.ast.query_expr(initializer).span(&compilation_unit.ast);
+        compilation_unit.ast.set_kind(initializer, ExprKind::Number(NumberExpr {
+            number: 0,
+            base: NumberBase::Decimal,
+            token: Token::new(TokenKind::Number(0), lexer::TextSpan::new(span.start, span.end, "0".to_string())),
+        }));
+
+        assert!(!same_types(&before, &compilation_unit.ast));
+    }
+
+    #[test]
+    pub fn should_call_function_defined_later() {
+        let input = "\
+        let main = func -> int {
+            return double(answer())
+        }
+        let double = func (n: int) -> int {
+            return n * 2
+        }
+        let answer = func -> int {
+            return 21
+        }
+        ";
+
+        let mut compilation_unit = compile(input).expect("Failed to compile");
+
+        assert_eq!(compilation_unit.evaluate(), Ok(Some(Value::Number(42))));
+    }
+
+    #[test]
+    pub fn should_type_discarded_expression_as_void() {
+        let input = "\
+        _ = 1 + 2
+        _ = true
+        ";
+
+        let compilation_unit = compile(input).expect("Failed to compile");
+        let discards: Vec<_> = compilation_unit.ast.expressions.iter()
+            .filter(|expr| matches!(expr.kind, ExprKind::Discard(_)))
+            .collect();
+
+        assert_eq!(discards.len(), 2);
+        assert!(discards.iter().all(|expr| expr.ty == Type::Void));
+    }
+
+    #[test]
+    pub fn should_record_stats_of_every_phase_when_enabled() {
+        let options = CompilationOptions { collect_stats: true, ..Default::default() };
+
+        let compilation_unit = CompilationUnit::compile_with_options("let a = 1 + 2", &options).expect("Failed to compile");
+
+        let stats = compilation_unit.stats.expect("Expected phase stats");
+        let phases: Vec<(&str, usize)> = stats.phases.iter().map(|stat| (stat.phase, stat.processed)).collect();
+        // 11 tokens including whitespace, plus the end of input
+        assert_eq!(phases, vec![("lex", 12), ("parse", 4), ("validate", 4), ("resolve", 4)]);
+        assert!(stats.phases.iter().all(|stat| stat.duration >= Duration::ZERO));
+        assert_eq!(stats.to_string().lines().count(), 5);
+    }
+
+    #[test]
+    pub fn should_not_record_stats_by_default() {
+        let compilation_unit = compile("let a = 1").expect("Failed to compile");
+
+        assert!(compilation_unit.stats.is_none());
+    }
+
+    #[test]
+    pub fn should_return_ast_when_stopping_after_parse_despite_type_error() {
+        let input = "\
+        let a: bool = 1
+        ";
+
+        let output = CompilationUnit::compile_until(input, &CompilationOptions::default(), StopAfter::Parse);
+
+        assert!(matches!(output, Ok(PhaseOutput::Ast(_))));
+    }
+
+    #[test]
+    pub fn should_report_type_error_when_stopping_after_typecheck() {
+        let input = "\
+        let a: bool = 1
+        ";
+
+        let output = CompilationUnit::compile_until(input, &CompilationOptions::default(), StopAfter::Typecheck);
+
+        let diagnostics = match output {
+            Err(diagnostics) => diagnostics,
+            Ok(_) => panic!("Expected diagnostics"),
+        };
+        let messages: Vec<String> = diagnostics.borrow().diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+        assert_eq!(messages, vec!["Expected type 'bool', found 'int'"]);
+    }
+
+    #[test]
+    pub fn should_report_panicking_pass_as_internal_compiler_error() {
+        let result: Result<(), _> = catch_internal_errors(|| panic!("pass exploded"));
+
+        let messages: Vec<String> = result.unwrap_err().iter().map(|diagnostic| diagnostic.message.clone()).collect();
+        assert_eq!(messages, vec!["Internal compiler error: pass exploded"]);
+    }
+
+    #[test]
+    pub fn should_compile_normally_when_catching_internal_errors() {
+        let options = CompilationOptions { catch_internal_errors: true, ..Default::default() };
+
+        assert!(compile_silently_with_options("let a = 1", &options).is_ok());
+        assert!(compile_silently_with_options("let a = b", &options).is_err());
+    }
+
+    #[test]
+    pub fn should_call_nested_function_within_its_block() {
+        let input = "\
+        let main = func -> int {
+            let helper = func (n: int) -> int {
+                return n + 1
+            }
+            return helper(41)
+        }
+        ";
+
+        let mut compilation_unit = compile(input).expect("Failed to compile");
+
+        assert_eq!(compilation_unit.evaluate(), Ok(Some(Value::Number(42))));
+    }
+
+    #[test]
+    pub fn should_report_nested_function_used_outside_of_its_block() {
+        let input = "\
+        let outer = func -> int {
+            {
+                let helper = func -> int {
+                    return 1
+                }
+                helper()
+            }
+            return helper()
+        }
+        let a = helper()
+        ";
+
+        let diagnostics = compile(input).err().expect("Expected diagnostics");
+
+        let undeclared: Vec<usize> = diagnostics.iter()
+            .filter(|diagnostic| diagnostic.message == "Undeclared variable 'helper'")
+            .map(|diagnostic| diagnostic.span.start)
+            .collect();
+        let return_use = input.find("return helper()").unwrap() + 7;
+        let top_level_use = input.rfind("helper()").unwrap();
+        assert_eq!(undeclared, vec![return_use, top_level_use]);
+    }
+
+    #[test]
+    pub fn should_resolve_predeclared_globals() {
+        let options = CompilationOptions {
+            predeclared_globals: vec![("VERSION".to_string(), Type::Int), ("PI".to_string(), Type::Float)],
+            ..Default::default()
+        };
+
+        assert!(compile_silently_with_options("let a: float = PI\nlet b = VERSION + 1", &options).is_ok());
+        let diagnostics = compile_silently_with_options("let a = TAU", &options).err().expect("Expected diagnostics");
+        assert_eq!(diagnostics[0].message, "Undeclared variable 'TAU'");
+    }
+
+    /// Deterministic xorshift, so a failing input can be reproduced.
+    fn random_inputs(count: usize) -> Vec<Vec<u8>> {
+        let alphabet: &[u8] = b"let func if else while return break continue extern rec true false _ a b 0 9 0x 0b ( ) { } , : ; = == != < <= > >= + - * ** / & | ^ ~ -> @ # \n \xff\xfe\xc3";
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..count).map(|_| {
+            let length = (next() % 64) as usize;
+            (0..length).map(|_| match next() % 4 {
+                0 => next() as u8,
+                _ => alphabet[(next() % alphabet.len() as u64) as usize],
+            }).collect()
+        }).collect()
+    }
+
+    #[test]
+    pub fn should_parse_arbitrary_bytes_without_panicking() {
+        let mut inputs = random_inputs(2000);
+        inputs.extend([
+            Vec::new(),
+            vec![0xff, 0xfe, 0xfd],
+            "let é = «ü»".as_bytes().to_vec(),
+            b"99999999999999999999 0xffffffffffffffffff".to_vec(),
+            b"func (".to_vec(),
+            b"let a: = _ = -> ) }".to_vec(),
+            "(".repeat(10_000).into_bytes(),
+            "{".repeat(10_000).into_bytes(),
+            "-".repeat(10_000).into_bytes(),
+            "a = ".repeat(10_000).into_bytes(),
+            "a ** ".repeat(10_000).into_bytes(),
+            "if a ".repeat(10_000).into_bytes(),
+            "a ".repeat(100_000).into_bytes(),
+        ]);
+
+        for input in &inputs {
+            if let Err(diagnostics) = parse_bytes(input) {
+                assert!(
+                    diagnostics.iter().all(|diagnostic| !diagnostic.message.starts_with("Internal compiler error")),
+                    "Parser panicked on {:?}: {:?}",
+                    String::from_utf8_lossy(input),
+                    diagnostics,
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn should_report_too_deeply_nested_bytes_once() {
+        let input = format!("let a = {}1{}", "(".repeat(1000), ")".repeat(1000));
+
+        let diagnostics = parse_bytes(input.as_bytes()).err().expect("Expected diagnostics");
+
+        let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(messages, vec!["Expression is nested too deeply, exceeding the limit of 64"]);
+    }
+
+    #[test]
+    pub fn should_parse_valid_bytes() {
+        let ast = parse_bytes(b"let a = (1 + 2) * 3").expect("Failed to parse");
+
+        assert_eq!(ast.items.len(), 1);
+    }
+
+    #[test]
+    pub fn should_not_use_predeclared_global_as_entry_point() {
+        let options = CompilationOptions {
+            entry_point: Some("VERSION".to_string()),
+            predeclared_globals: vec![("VERSION".to_string(), Type::Int)],
+            ..Default::default()
+        };
+
+        let diagnostics = compile_silently_with_options("let a = VERSION", &options).err().expect("Expected diagnostics");
+
+        assert_eq!(diagnostics[0].message, "No entry function 'VERSION' found");
+    }
+
+    #[test]
+    pub fn should_reject_implicit_widening_in_strict_mode() {
+        let strict = CompilationOptions { strictness: TypeCheckStrictness::Strict, ..Default::default() };
+
+        assert!(compile("let x: float = 3").is_ok());
+        let diagnostics = compile_silently_with_options("let x: float = 3", &strict).err().expect("Expected diagnostics");
+        assert_eq!(diagnostics[0].message, "Expected type 'float', found 'int'");
+    }
+}
@@ -2,6 +2,7 @@ use std::{cell::RefCell, rc::Rc};
 
 use crate::ast::lexer::{Token, TokenKind};
 use crate::text::span::TextSpan;
+use crate::typings::TypeError;
 
 pub mod printer;
 
@@ -55,6 +56,40 @@ impl DiagnosticsBag {
             token.span.clone(),
         );
     }
+    pub fn report_too_many_tokens(&mut self, token: &Token, token_count: usize, max_tokens: usize) {
+        self.report_error(
+            format!("Input has {} tokens, exceeding the limit of {}", token_count, max_tokens),
+            token.span.clone(),
+        );
+    }
+    pub fn report_number_too_large(&mut self, token: &Token) {
+        self.report_error(
+            format!("Number '{}' is too large, the maximum is {}", token.span.literal, i64::MAX),
+            token.span.clone(),
+        );
+    }
+    pub fn report_nesting_too_deep(&mut self, token: &Token, max_depth: usize) {
+        self.report_error(
+            format!("Expression is nested too deeply, exceeding the limit of {}", max_depth),
+            token.span.clone(),
+        );
+    }
+    pub fn report_unexpected_character(&mut self, token: &Token) {
+        self.report_error(
+            format!("Unexpected character '{}'", token.span.literal),
+            token.span.clone(),
+        );
+    }
+    pub fn report_unclosed_delimiter(&mut self, opening: &Token, expected: &TokenKind, eof: &Token) {
+        self.report_error(
+            format!("Unclosed delimiter '{}' opened here", opening.span.literal),
+            opening.span.clone(),
+        );
+        self.report_error(
+            format!("Expected '{}' to close delimiter, found end of input", expected),
+            eof.span.clone(),
+        );
+    }
     pub fn report_expected_expression(&mut self, token: &Token) {
         self.report_error(
             format!("Expected expression, found <{}>", token.kind),
@@ -107,10 +142,17 @@ impl DiagnosticsBag {
         );
     }
 
-    pub fn report_undeclared_type(&mut self, token: &Token) {
+    pub fn report_void_initializer(&mut self, identifier: &Token, span: &TextSpan) {
         self.report_error(
-            format!("Undeclared type '{}'", token.span.literal),
-            token.span.clone(),
+            format!("Cannot initialize variable '{}' with a value of type 'void'", identifier.span.literal),
+            span.clone(),
+        );
+    }
+
+    pub fn report_undeclared_type(&mut self, error: &TypeError) {
+        self.report_error(
+            error.to_string(),
+            error.span.clone(),
         );
     }
     pub fn report_cannot_return_outside_function(&mut self, token: &Token) {
@@ -125,6 +167,83 @@ impl DiagnosticsBag {
             token.span.clone(),
         );
     }
+
+    pub fn report_internal_compiler_error(&mut self, message: &str) {
+        self.report_error(
+            format!("Internal compiler error: {}", message),
+            TextSpan::new(0, 0, String::new()),
+        );
+    }
+
+    pub fn report_no_entry_function_found(&mut self, name: &str) {
+        self.report_error(
+            format!("No entry function '{}' found", name),
+            TextSpan::new(0, 0, String::new()),
+        );
+    }
+
+    pub fn report_multiple_entry_functions(&mut self, token: &Token) {
+        self.report_error(
+            format!("Multiple entry functions '{}' found", token.span.literal),
+            token.span.clone(),
+        );
+    }
+
+    pub fn report_invalid_entry_point_signature(&mut self, token: &Token) {
+        self.report_error(
+            format!("Entry point '{}' must be of type '() -> int' or '() -> void'", token.span.literal),
+            token.span.clone(),
+        );
+    }
+
+    pub fn report_too_many_locals(&mut self, token: &Token, max_locals: usize) {
+        self.report_error(
+            format!("Function declares more than {} locals", max_locals),
+            token.span.clone(),
+        );
+    }
+
+    pub fn report_division_by_zero(&mut self, span: &TextSpan) {
+        self.report_error(
+            format!("Division by zero"),
+            span.clone(),
+        );
+    }
+
+    pub fn report_cannot_use_break_outside_of_loop(&mut self, token: &Token) {
+        self.report_error(
+            format!("Cannot use 'break' outside of loop"),
+            token.span.clone(),
+        );
+    }
+
+    pub fn report_cannot_use_continue_outside_of_loop(&mut self, token: &Token) {
+        self.report_error(
+            format!("Cannot use 'continue' outside of loop"),
+            token.span.clone(),
+        );
+    }
+
+    pub fn report_unused_parameter(&mut self, token: &Token) {
+        self.report_warning(
+            format!("Parameter '{}' is never used, prefix it with '_' if this is intended", token.span.literal),
+            token.span.clone(),
+        );
+    }
+
+    pub fn report_expression_without_effect(&mut self, span: &TextSpan) {
+        self.report_warning(
+            format!("Expression statement has no effect, its value is discarded"),
+            span.clone(),
+        );
+    }
+
+    pub fn report_identical_operands(&mut self, span: &TextSpan, operator: &Token, simplification: &str) {
+        self.report_warning(
+            format!("Both operands of '{}' are identical, consider replacing with '{}'", operator.span.literal, simplification),
+            span.clone(),
+        );
+    }
 }
 
 #[cfg(test)]
@@ -242,7 +361,15 @@ mod test {
     #[test]
     fn should_report_bad_token() {
         let input = "let a = 8 «@» 2";
-        let expected = vec!["Expected expression, found <Bad>"];
+        let expected = vec!["Unexpected character '@'"];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    fn should_report_number_too_large() {
+        let input = "let a = «99999999999999999999»";
+        let expected = vec!["Number '99999999999999999999' is too large, the maximum is 9223372036854775807"];
 
         assert_diagnostics(input, expected);
     }
@@ -571,4 +698,131 @@ b
 
         assert_diagnostics(input, expected);
     }
+
+    #[test]
+    pub fn should_type_check_call_to_extern_function() {
+        let input = "\
+        extern func now() -> int
+        let a: bool = «now()»
+        ";
+
+        let expected = vec!["Expected type 'bool', found 'int'"];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_report_break_outside_of_loop() {
+        let input = "\
+        «break»
+        ";
+
+        let expected = vec!["Cannot use 'break' outside of loop"];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_report_continue_in_function_outside_of_loop() {
+        let input = "\
+        let f = func {
+            «continue»
+        }
+        ";
+
+        let expected = vec!["Cannot use 'continue' outside of loop"];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_report_break_in_function_nested_in_loop() {
+        let input = "\
+        while true {
+            let f = func {
+                «break»
+            }
+        }
+        ";
+
+        let expected = vec!["Cannot use 'break' outside of loop"];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_allow_break_continue_and_return_in_their_contexts() {
+        let input = "\
+        let f = func -> int {
+            let i = 0
+            while true {
+                if i == 10 {
+                    break
+                }
+                i = i + 1
+                continue
+            }
+            return i
+        }
+        ";
+
+        let expected = vec![];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_report_bitwise_not_on_bool_operand() {
+        let input = "\
+        let a = ~«true»
+        ";
+
+        let expected = vec!["Expected type 'int', found 'bool'"];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_report_unclosed_parenthesis() {
+        let input = "\
+        let a = «(»b + a
+        «»";
+
+        let expected = vec![
+            "Unclosed delimiter '(' opened here",
+            "Expected ')' to close delimiter, found end of input",
+        ];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_report_unclosed_brace() {
+        let input = "\
+        let a = func «{»
+            let b = a
+        «»";
+
+        let expected = vec![
+            "Unclosed delimiter '{' opened here",
+            "Expected '}' to close delimiter, found end of input",
+        ];
+
+        assert_diagnostics(input, expected);
+    }
+
+    #[test]
+    pub fn should_report_void_initializer() {
+        let input = "\
+        let a = «_ = 1»
+        let b = «{ _ = 2 }»
+        ";
+
+        let expected = vec![
+            "Cannot initialize variable 'a' with a value of type 'void'",
+            "Cannot initialize variable 'b' with a value of type 'void'",
+        ];
+
+        assert_diagnostics(input, expected);
+    }
 }
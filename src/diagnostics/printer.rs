@@ -1,7 +1,7 @@
 use crate::diagnostics::Diagnostic;
 use crate::text::SourceText;
 use std::cmp;
-use termion::color::{Fg, Red, Reset};
+use crate::color::Color;
 
 pub struct DiagnosticsPrinter<'a> {
     text: &'a SourceText,
@@ -39,9 +39,9 @@ impl<'a> DiagnosticsPrinter<'a> {
         format!(
             "{}{}{}{}{}\n{}\n{}\n{}",
             prefix,
-            Fg(Red),
+            Color::Red.fg_str(),
             span,
-            Fg(Reset),
+            Color::Reset.fg_str(),
             suffix,
             arrow_pointers,
             arrow_line,
@@ -1,9 +1,13 @@
 // building a lexer
 // A lexer takes a line and converts into a lexical token
-use crate::compilation_unit::CompilationUnit;
+use crate::compilation_unit::{CompilationOptions, CompilationUnit, PhaseOutput, StopAfter};
 mod ast;
+mod color;
 mod compilation_unit;
 mod diagnostics;
+mod mangling;
+mod passes;
+mod stable_hash;
 mod text;
 mod typings;
 fn main() -> Result<(), ()> {
@@ -46,7 +50,48 @@ fn main() -> Result<(), ()> {
         let e = c + d
         e
     ";
-    let mut compilation_unit = CompilationUnit::compile(input).map_err(|_| ())?;
+    let options = CompilationOptions {
+        collect_stats: std::env::args().any(|arg| arg == "--stats"),
+        ..Default::default()
+    };
+    if let Some(stop_after) = parse_stop_after(std::env::args().skip(1))? {
+        let output = CompilationUnit::compile_until(input, &options, stop_after).map_err(|_| ())?;
+        match output {
+            PhaseOutput::Tokens(tokens) => tokens.iter().for_each(|token| println!("{:?}", token)),
+            PhaseOutput::Ast(mut ast) => ast.visualize(),
+            PhaseOutput::Program(mut compilation_unit) => {
+                compilation_unit.ast.visualize();
+                print_stats(&compilation_unit);
+            }
+        }
+        return Ok(());
+    }
+    let mut compilation_unit = CompilationUnit::compile_with_options(input, &options).map_err(|_| ())?;
+    print_stats(&compilation_unit);
     compilation_unit.run();
     Ok(())
 }
+
+/// Prints the phase timings if the program was compiled with `--stats`.
+fn print_stats(compilation_unit: &CompilationUnit) {
+    if let Some(stats) = &compilation_unit.stats {
+        print!("{}", stats);
+    }
+}
+
+/// Reads `--stop-after <lex|parse|resolve|typecheck>` from the command line arguments.
+fn parse_stop_after(mut args: impl Iterator<Item = String>) -> Result<Option<StopAfter>, ()> {
+    while let Some(arg) = args.next() {
+        if arg == "--stop-after" {
+            let phase = args.next().unwrap_or_default();
+            return match StopAfter::from_str(&phase) {
+                Some(stop_after) => Ok(Some(stop_after)),
+                None => {
+                    eprintln!("Unknown phase '{}', expected one of lex, parse, resolve, typecheck", phase);
+                    Err(())
+                }
+            };
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use rs_compiler::Idx;
+
+use crate::ast::{ExprKind, StmtKind};
+use crate::compilation_unit::{CompilationUnit, FunctionIdx};
+use crate::stable_hash::StableHasher;
+use crate::typings::Type;
+
+/// Name given to functions that are not bound to a name, e.g. a `func` passed as an argument.
+const ANONYMOUS_FUNCTION_NAME: &str = "anonymous";
+
+/// Turns a function's source name into the symbol name emitted by a backend.
+///
+/// `is_unique` tells whether the function is the only one in the program with that source name.
+pub trait Mangler {
+    fn mangle(&self, function_idx: FunctionIdx, name: &str, is_unique: bool) -> String;
+}
+
+/// Uses the source name as is, even if several functions share it.
+pub struct PlainMangler;
+
+impl Mangler for PlainMangler {
+    fn mangle(&self, function_idx: FunctionIdx, name: &str, is_unique: bool) -> String {
+        name.to_string()
+    }
+}
+
+/// The default scheme: keeps unique names readable and appends `$idx` to names shared by several
+/// functions.
+pub struct IndexMangler;
+
+impl Mangler for IndexMangler {
+    fn mangle(&self, function_idx: FunctionIdx, name: &str, is_unique: bool) -> String {
+        if is_unique {
+            name.to_string()
+        } else {
+            format!("{}${}", name, function_idx.as_index())
+        }
+    }
+}
+
+/// Appends a hash of the name and index to every name.
+///
+/// The hash is stable, so a program gets the same symbol names from every build of the compiler.
+pub struct HashMangler;
+
+impl Mangler for HashMangler {
+    fn mangle(&self, function_idx: FunctionIdx, name: &str, is_unique: bool) -> String {
+        let mut hasher = StableHasher::new();
+        hasher.write_str(name);
+        hasher.write_usize(function_idx.as_index());
+        format!("{}${:016x}", name, hasher.finish())
+    }
+}
+
+/// Computes the symbol name of every function in `program` using `mangler`.
+///
+/// A function's source name is the name of the extern or `let` declaring it.
+pub fn symbol_names(program: &CompilationUnit, mangler: &dyn Mangler) -> HashMap<FunctionIdx, String> {
+    let source_names = source_names(program);
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for name in source_names.values() {
+        *name_counts.entry(name.as_str()).or_insert(0) += 1;
+    }
+    source_names.iter()
+        .map(|(function_idx, name)| {
+            let is_unique = name_counts[name.as_str()] == 1;
+            (*function_idx, mangler.mangle(*function_idx, name, is_unique))
+        })
+        .collect()
+}
+
+/// Renders `names` one function per line, as `<function index>: <symbol name>`, in index order.
+pub fn render_symbol_names(names: &HashMap<FunctionIdx, String>) -> String {
+    let mut names: Vec<(&FunctionIdx, &String)> = names.iter().collect();
+    names.sort_by_key(|(function_idx, _)| function_idx.as_index());
+    names.iter().map(|(function_idx, name)| format!("{}: {}\n", function_idx.as_index(), name)).collect()
+}
+
+fn source_names(program: &CompilationUnit) -> HashMap<FunctionIdx, String> {
+    let mut names: HashMap<FunctionIdx, String> = program.global_scope.functions.indexed_iter()
+        .map(|(function_idx, function)| {
+            let name = function.name.clone().unwrap_or_else(|| ANONYMOUS_FUNCTION_NAME.to_string());
+            (function_idx, name)
+        })
+        .collect();
+    let ast = &program.ast;
+    for stmt in ast.statements.iter() {
+        if let StmtKind::Let(let_stmt) = &stmt.kind {
+            let initializer = ast.query_expr(let_stmt.initializer);
+            if let (ExprKind::Func(_), Type::Function(function_idx)) = (&initializer.kind, &initializer.ty) {
+                names.insert(*function_idx, let_stmt.identifier.span.literal.clone());
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use rs_compiler::Idx;
+
+    use crate::compilation_unit::{CompilationUnit, FunctionIdx};
+    use crate::mangling::{render_symbol_names, symbol_names, HashMangler, IndexMangler};
+
+    #[test]
+    pub fn should_disambiguate_shared_names_and_keep_unique_ones() {
+        let input = "\
+        let f = func -> int {
+            return 1
+        }
+        let f = func -> int {
+            return 2
+        }
+        let g = func {}
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+
+        let names = symbol_names(&compilation_unit, &IndexMangler);
+
+        assert_eq!(names[&FunctionIdx::new(0)], "f$0");
+        assert_eq!(names[&FunctionIdx::new(1)], "f$1");
+        assert_eq!(names[&FunctionIdx::new(2)], "g");
+    }
+
+    #[test]
+    pub fn should_hash_deterministically() {
+        let compilation_unit = CompilationUnit::compile("let f = func {}").expect("Failed to compile");
+
+        let first = symbol_names(&compilation_unit, &HashMangler);
+        let second = symbol_names(&compilation_unit, &HashMangler);
+
+        assert_eq!(first, second);
+        // pinned, so a change of the hash algorithm does not go unnoticed
+        assert_eq!(first[&FunctionIdx::new(0)], "f$459909fecf483766");
+    }
+
+    #[test]
+    pub fn should_render_symbol_names_in_index_order() {
+        let input = "\
+        let h = func {}
+        let g = func {}
+        let f = func {}
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+
+        let rendered = render_symbol_names(&symbol_names(&compilation_unit, &IndexMangler));
+
+        assert_eq!(rendered, "0: h\n1: g\n2: f\n");
+    }
+}
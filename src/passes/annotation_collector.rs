@@ -0,0 +1,104 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BoolExpr, Expr, ExprId, ExternStmt, FuncDeclParameter, FuncExpr, LetStmt, NumberExpr, RecExpr, StaticTypeAnnotation, Stmt, UnaryExpr, VarExpr};
+use crate::text::span::TextSpan;
+use crate::typings::Type;
+
+/// Returns the type name span of every type annotation in `ast` with the type it resolves to,
+/// in source order.
+///
+/// Annotations naming an unknown type are kept with `None`.
+pub fn collect_annotations(ast: &mut Ast) -> Vec<(TextSpan, Option<Type>)> {
+    let mut collector = AnnotationCollector { annotations: Vec::new() };
+    ast.visit(&mut collector);
+    let mut annotations = collector.annotations;
+    annotations.sort_by_key(|(span, _)| span.start);
+    annotations
+}
+
+struct AnnotationCollector {
+    annotations: Vec<(TextSpan, Option<Type>)>,
+}
+
+impl AnnotationCollector {
+    fn add_annotation(&mut self, type_annotation: &StaticTypeAnnotation) {
+        let ty = Type::from_annotation(type_annotation).ok();
+        let span = &type_annotation.type_name.span;
+        self.annotations.push((TextSpan::new(span.start, span.end, span.literal.clone()), ty));
+    }
+
+    fn add_parameters(&mut self, parameters: &[FuncDeclParameter]) {
+        for parameter in parameters {
+            self.add_annotation(&parameter.type_annotation);
+        }
+    }
+}
+
+impl ASTVisitor for AnnotationCollector {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        self.add_parameters(&func_expr.decl.parameters);
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_extern_statement(&mut self, ast: &mut Ast, extern_statement: &ExternStmt, stmt: &Stmt) {
+        self.add_parameters(&extern_statement.decl.parameters);
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        if let Some(type_annotation) = &let_statement.type_annotation {
+            self.add_annotation(type_annotation);
+        }
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::ast::Ast;
+    use crate::ast::lexer::Lexer;
+    use crate::ast::parser::Parser;
+    use crate::diagnostics::{DiagnosticsBag, DiagnosticsBagCell};
+    use crate::passes::annotation_collector::collect_annotations;
+    use crate::typings::Type;
+
+    #[test]
+    pub fn should_collect_resolved_and_unresolved_annotations() {
+        let input = "let a: int = 1\nlet f = func (b: foo) {}";
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        let mut lexer = Lexer::new(input, Rc::clone(&diagnostics));
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            tokens.push(token);
+        }
+        let mut ast = Ast::new();
+        Parser::new(tokens, diagnostics, &mut ast).parse();
+
+        let annotations = collect_annotations(&mut ast);
+
+        assert_eq!(annotations.len(), 2);
+        let (int_span, int_type) = &annotations[0];
+        assert_eq!(int_span.literal, "int");
+        assert!(matches!(int_type, Some(Type::Int)));
+        let (foo_span, foo_type) = &annotations[1];
+        assert_eq!(foo_span.literal, "foo");
+        assert!(foo_type.is_none());
+    }
+}
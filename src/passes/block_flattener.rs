@@ -0,0 +1,152 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BlockExpr, BoolExpr, Expr, ExprId, ExprKind, FuncExpr, LetStmt, NumberExpr, RecExpr, Stmt, StmtId, StmtKind, UnaryExpr, VarExpr};
+use crate::text::span::TextSpan;
+
+/// Removes redundant nesting of blocks.
+///
+/// A block statement is spliced into the enclosing block when it is the only statement there,
+/// or when it declares nothing, so no variable can escape its scope or shadow an outer one.
+/// Empty block statements are dropped, except in last position, where they give the enclosing
+/// block its `void` value.
+pub struct BlockFlattener {}
+
+impl BlockFlattener {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn flatten(&mut self, ast: &mut Ast) {
+        ast.visit(self);
+    }
+
+    fn as_block(ast: &Ast, stmt_id: StmtId) -> Option<&BlockExpr> {
+        match &ast.query_stmt(stmt_id).kind {
+            StmtKind::Expr(expr_id) => match &ast.query_expr(*expr_id).kind {
+                ExprKind::Block(block) => Some(block),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn declares_variables(ast: &Ast, block: &BlockExpr) -> bool {
+        block.stmts.iter().any(|stmt_id| matches!(ast.query_stmt(*stmt_id).kind, StmtKind::Let(_) | StmtKind::Extern(_)))
+    }
+}
+
+impl ASTVisitor for BlockFlattener {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, expr: &Expr) {
+        for statement in &block_expr.stmts {
+            self.visit_statement(ast, *statement);
+        }
+        let is_only_statement = block_expr.stmts.len() == 1;
+        let mut stmts = Vec::new();
+        for (index, stmt_id) in block_expr.stmts.iter().enumerate() {
+            let is_last = index == block_expr.stmts.len() - 1;
+            match Self::as_block(ast, *stmt_id) {
+                Some(inner) if inner.stmts.is_empty() && !is_last => {}
+                Some(inner) if is_only_statement || !Self::declares_variables(ast, inner) => {
+                    stmts.extend(inner.stmts.iter().copied());
+                }
+                _ => stmts.push(*stmt_id),
+            }
+        }
+        ast.set_kind(expr.id, ExprKind::Block(BlockExpr {
+            left_brace: block_expr.left_brace.clone(),
+            stmts,
+            right_brace: block_expr.right_brace.clone(),
+        }));
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::printer::test::assert_ast_eq;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::passes::block_flattener::BlockFlattener;
+
+    fn assert_flattens_to(input: &str, expected: &str) {
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        BlockFlattener::new().flatten(&mut compilation_unit.ast);
+        assert_ast_eq(expected, &mut compilation_unit.ast);
+    }
+
+    #[test]
+    pub fn should_flatten_trivially_nested_block() {
+        let input = "\
+        let a = {
+            {
+                {
+                    let b = 1
+                    b
+                }
+            }
+        }
+        ";
+        let expected = "\
+        let a = {
+            let b = 1
+            b
+        }
+        ";
+
+        assert_flattens_to(input, expected);
+    }
+
+    #[test]
+    pub fn should_drop_empty_block_statement() {
+        let input = "\
+        let a = {
+            {}
+            1
+        }
+        ";
+        let expected = "\
+        let a = {
+            1
+        }
+        ";
+
+        assert_flattens_to(input, expected);
+    }
+
+    #[test]
+    pub fn should_not_flatten_block_introducing_shadowed_variable() {
+        let input = "\
+        let a = {
+            let x = 1
+            {
+                let x = 2
+                x
+            }
+            x
+        }
+        ";
+
+        assert_flattens_to(input, input);
+    }
+}
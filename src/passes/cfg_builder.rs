@@ -0,0 +1,248 @@
+use std::fmt::{Display, Formatter};
+
+use rs_compiler::{Idx, idx, IdxVec};
+
+use crate::ast::{Ast, ExprId, ExprKind, IfExpr, StmtId, StmtKind, WhileStmt};
+
+idx!(BasicBlockIdx);
+
+/// Statements that run one after the other, with the blocks control may continue in.
+#[derive(Debug, Clone, Default)]
+pub struct BasicBlock {
+    pub statements: Vec<StmtId>,
+    pub successors: Vec<BasicBlockIdx>,
+}
+
+/// Control-flow graph of a single function body. The first block is the entry.
+#[derive(Debug)]
+pub struct ControlFlowGraph {
+    pub blocks: IdxVec<BasicBlockIdx, BasicBlock>,
+}
+
+/// Renders one block per line, as `bb<index>: <statement count> statements -> <successors>`.
+impl Display for ControlFlowGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (block_idx, block) in self.blocks.indexed_iter() {
+            write!(f, "bb{}: {} statements", block_idx.as_index(), block.statements.len())?;
+            if !block.successors.is_empty() {
+                let successors: Vec<String> = block.successors.iter().map(|successor| format!("bb{}", successor.as_index())).collect();
+                write!(f, " -> {}", successors.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where `break` and `continue` jump to in the innermost loop.
+struct LoopTargets {
+    header: BasicBlockIdx,
+    exit: BasicBlockIdx,
+}
+
+/// Builds the [`ControlFlowGraph`] of a function.
+///
+/// Blocks are split at `if` and `while` statements and after `return`, `break` and `continue`.
+/// The statement holding an `if` or `while` ends the block evaluating its condition. An `if`
+/// nested inside another expression, such as a `let` initializer, does not split its block.
+pub struct CfgBuilder {
+    blocks: IdxVec<BasicBlockIdx, BasicBlock>,
+    /// Block the next statement is added to, `None` after a jump until a statement needs one.
+    current: Option<BasicBlockIdx>,
+    loops: Vec<LoopTargets>,
+}
+
+impl CfgBuilder {
+    pub fn new() -> Self {
+        Self { blocks: IdxVec::new(), current: None, loops: Vec::new() }
+    }
+
+    /// Builds the graph of the body of the `func` expression `func_expr`.
+    pub fn build(mut self, ast: &Ast, func_expr: ExprId) -> ControlFlowGraph {
+        let entry = self.new_block();
+        self.current = Some(entry);
+        if let ExprKind::Func(func_expr) = &ast.query_expr(func_expr).kind {
+            if let Some(body) = func_expr.decl.body {
+                self.build_branch(ast, body);
+            }
+        }
+        ControlFlowGraph { blocks: self.blocks }
+    }
+
+    fn new_block(&mut self) -> BasicBlockIdx {
+        self.blocks.push(BasicBlock::default())
+    }
+
+    fn add_edge(&mut self, from: BasicBlockIdx, to: BasicBlockIdx) {
+        self.blocks[from].successors.push(to);
+    }
+
+    fn current_block(&mut self) -> BasicBlockIdx {
+        match self.current {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.current = Some(block);
+                block
+            }
+        }
+    }
+
+    fn append(&mut self, stmt_id: StmtId) -> BasicBlockIdx {
+        let block = self.current_block();
+        self.blocks[block].statements.push(stmt_id);
+        block
+    }
+
+    /// Adds the statements of a block, or the branches of an `else if`.
+    fn build_branch(&mut self, ast: &Ast, expr_id: ExprId) {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Block(block) => {
+                for stmt_id in &block.stmts {
+                    self.build_statement(ast, *stmt_id);
+                }
+            }
+            ExprKind::If(if_expr) => self.build_if(ast, None, if_expr),
+            _ => {}
+        }
+    }
+
+    fn build_statement(&mut self, ast: &Ast, stmt_id: StmtId) {
+        match &ast.query_stmt(stmt_id).kind {
+            StmtKind::Expr(expr_id) => match &ast.query_expr(*expr_id).kind {
+                ExprKind::If(if_expr) => self.build_if(ast, Some(stmt_id), if_expr),
+                ExprKind::Block(_) => self.build_branch(ast, *expr_id),
+                _ => {
+                    self.append(stmt_id);
+                }
+            },
+            StmtKind::While(while_stmt) => self.build_while(ast, stmt_id, while_stmt),
+            StmtKind::Return(_) => {
+                self.append(stmt_id);
+                self.current = None;
+            }
+            StmtKind::Break(_) => {
+                let block = self.append(stmt_id);
+                if let Some(exit) = self.loops.last().map(|targets| targets.exit) {
+                    self.add_edge(block, exit);
+                }
+                self.current = None;
+            }
+            StmtKind::Continue(_) => {
+                let block = self.append(stmt_id);
+                if let Some(header) = self.loops.last().map(|targets| targets.header) {
+                    self.add_edge(block, header);
+                }
+                self.current = None;
+            }
+            StmtKind::Let(_) | StmtKind::Extern(_) => {
+                self.append(stmt_id);
+            }
+        }
+    }
+
+    fn build_if(&mut self, ast: &Ast, stmt_id: Option<StmtId>, if_expr: &IfExpr) {
+        let condition = match stmt_id {
+            Some(stmt_id) => self.append(stmt_id),
+            None => self.current_block(),
+        };
+        let then_block = self.new_block();
+        self.add_edge(condition, then_block);
+        self.current = Some(then_block);
+        self.build_branch(ast, if_expr.then_branch);
+        let then_end = self.current;
+        let else_end = match &if_expr.else_branch {
+            Some(else_branch) => {
+                let else_block = self.new_block();
+                self.add_edge(condition, else_block);
+                self.current = Some(else_block);
+                self.build_branch(ast, else_branch.expr);
+                self.current
+            }
+            None => Some(condition),
+        };
+        let join = self.new_block();
+        for end in [then_end, else_end].into_iter().flatten() {
+            self.add_edge(end, join);
+        }
+        self.current = Some(join);
+    }
+
+    fn build_while(&mut self, ast: &Ast, stmt_id: StmtId, while_stmt: &WhileStmt) {
+        let before = self.current_block();
+        let header = self.new_block();
+        self.add_edge(before, header);
+        self.blocks[header].statements.push(stmt_id);
+        let body = self.new_block();
+        let exit = self.new_block();
+        self.add_edge(header, body);
+        self.add_edge(header, exit);
+        self.loops.push(LoopTargets { header, exit });
+        self.current = Some(body);
+        self.build_branch(ast, while_stmt.body);
+        if let Some(end) = self.current {
+            self.add_edge(end, header);
+        }
+        self.loops.pop();
+        self.current = Some(exit);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::{ExprId, ExprKind};
+    use crate::compilation_unit::CompilationUnit;
+    use crate::passes::cfg_builder::{CfgBuilder, ControlFlowGraph};
+
+    fn build(input: &str) -> ControlFlowGraph {
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let ast = &compilation_unit.ast;
+        let func_expr: ExprId = ast.expressions.iter()
+            .find(|expr| matches!(expr.kind, ExprKind::Func(_)))
+            .map(|expr| expr.id)
+            .expect("Expected a function");
+        CfgBuilder::new().build(ast, func_expr)
+    }
+
+    #[test]
+    pub fn should_branch_from_condition_block_of_if() {
+        let input = "\
+        let f = func (a: int) -> int {
+            let b = a
+            if a > 0 {
+                b = 1
+            } else {
+                b = 2
+            }
+            return b
+        }
+        ";
+
+        let cfg = build(input);
+
+        assert_eq!(cfg.blocks.len(), 4);
+        assert_eq!(
+            cfg.to_string(),
+            "bb0: 2 statements -> bb1, bb2\nbb1: 1 statements -> bb3\nbb2: 1 statements -> bb3\nbb3: 1 statements\n",
+        );
+    }
+
+    #[test]
+    pub fn should_add_back_edge_from_loop_body_to_header() {
+        let input = "\
+        let f = func (a: int) -> int {
+            while a > 0 {
+                a = a - 1
+            }
+            return a
+        }
+        ";
+
+        let cfg = build(input);
+
+        assert_eq!(
+            cfg.to_string(),
+            "bb0: 0 statements -> bb1\nbb1: 1 statements -> bb2, bb3\nbb2: 1 statements -> bb1\nbb3: 1 statements\n",
+        );
+    }
+}
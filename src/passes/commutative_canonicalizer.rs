@@ -0,0 +1,150 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BinOpKind, BinaryExpr, BoolExpr, Expr, ExprId, ExprKind, FuncExpr, LetStmt, NumberExpr, RecExpr, Stmt, UnaryExpr, VarExpr};
+use crate::passes::constant_folder::ConstantFolder;
+use crate::text::span::TextSpan;
+
+/// Normalizes commutative binary expressions so that equivalent trees look the same.
+///
+/// Constant operands are moved to the right (`1 + x` becomes `x + 1`) and, for associative
+/// operators, constants of nested expressions are combined (`x + 1 + 2` becomes `x + 3`).
+/// Only literal operands are ever moved, so the evaluation order of side effects is unchanged.
+pub struct CommutativeCanonicalizer {}
+
+impl CommutativeCanonicalizer {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn canonicalize(&mut self, ast: &mut Ast) {
+        ast.visit(self);
+    }
+
+    fn is_commutative(operator: &BinOpKind) -> bool {
+        matches!(
+            operator,
+            BinOpKind::Plus
+                | BinOpKind::Multiply
+                | BinOpKind::Equals
+                | BinOpKind::NotEquals
+                | BinOpKind::BitwiseAnd
+                | BinOpKind::BitwiseOr
+                | BinOpKind::BitwiseXor
+        )
+    }
+
+    fn is_associative(operator: &BinOpKind) -> bool {
+        matches!(
+            operator,
+            BinOpKind::Plus
+                | BinOpKind::Multiply
+                | BinOpKind::BitwiseAnd
+                | BinOpKind::BitwiseOr
+                | BinOpKind::BitwiseXor
+        )
+    }
+
+    fn is_constant(ast: &Ast, expr_id: ExprId) -> bool {
+        ConstantFolder::constant_value(ast, expr_id).is_some()
+    }
+
+    /// Rewrites `(x op c1) op c2` into `x op (c1 op c2)`, returning whether it did so.
+    fn reassociate(ast: &mut Ast, binary_expression: &BinaryExpr, expr: &Expr) -> bool {
+        let inner = match &ast.query_expr(binary_expression.left).kind {
+            ExprKind::Binary(inner) if inner.operator.kind == binary_expression.operator.kind => inner.clone(),
+            _ => return false,
+        };
+        let inner_constant = ConstantFolder::constant_value(ast, inner.right);
+        let outer_constant = ConstantFolder::constant_value(ast, binary_expression.right);
+        let combined = match (inner_constant, outer_constant) {
+            (Some(left), Some(right)) => ConstantFolder::fold_binary(&binary_expression.operator.kind, left, right),
+            _ => None,
+        };
+        let combined = match combined {
+            Some(combined) => combined,
+            None => return false,
+        };
+        ConstantFolder::replace_with_constant(ast, binary_expression.right, combined);
+        ast.set_kind(expr.id, ExprKind::Binary(BinaryExpr {
+            left: inner.left,
+            operator: binary_expression.operator.clone(),
+            right: binary_expression.right,
+        }));
+        true
+    }
+}
+
+impl ASTVisitor for CommutativeCanonicalizer {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expression: &BinaryExpr, expr: &Expr) {
+        self.visit_expression(ast, binary_expression.left);
+        self.visit_expression(ast, binary_expression.right);
+        if !Self::is_commutative(&binary_expression.operator.kind) {
+            return;
+        }
+        let mut binary_expression = binary_expression.clone();
+        if Self::is_constant(ast, binary_expression.left) && !Self::is_constant(ast, binary_expression.right) {
+            binary_expression = BinaryExpr {
+                left: binary_expression.right,
+                operator: binary_expression.operator,
+                right: binary_expression.left,
+            };
+            ast.set_kind(expr.id, ExprKind::Binary(binary_expression.clone()));
+        }
+        if Self::is_associative(&binary_expression.operator.kind) {
+            Self::reassociate(ast, &binary_expression, expr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::printer::test::assert_ast_eq;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::passes::commutative_canonicalizer::CommutativeCanonicalizer;
+
+    fn assert_canonicalizes_to(input: &str, expected: &str) {
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        CommutativeCanonicalizer::new().canonicalize(&mut compilation_unit.ast);
+        assert_ast_eq(expected, &mut compilation_unit.ast);
+    }
+
+    #[test]
+    pub fn should_normalize_literal_on_either_side_to_same_tree() {
+        assert_canonicalizes_to("let x = 2\nlet a = 1 + x", "let x = 2\nlet a = x + 1");
+        assert_canonicalizes_to("let x = 2\nlet a = x + 1", "let x = 2\nlet a = x + 1");
+    }
+
+    #[test]
+    pub fn should_combine_constants_of_nested_commutative_expressions() {
+        assert_canonicalizes_to("let x = 2\nlet a = 1 + x + 2", "let x = 2\nlet a = x + 3");
+    }
+
+    #[test]
+    pub fn should_not_touch_non_commutative_operators() {
+        assert_canonicalizes_to("let x = 2\nlet a = x - 1", "let x = 2\nlet a = x - 1");
+        assert_canonicalizes_to("let x = 2\nlet a = 1 - x", "let x = 2\nlet a = 1 - x");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::ast::evaluator::Value;
+use crate::ast::lexer::{self, Token, TokenKind};
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BinOpKind, BinaryExpr, BoolExpr, Expr, ExprId, ExprKind, FuncExpr, LetStmt, NumberBase, NumberExpr, RecExpr, Stmt, UnOpKid, UnaryExpr, VarExpr};
+use crate::text::span::TextSpan;
+
+/// Replaces unary and binary expressions over constant operands with the literal they evaluate to.
+///
+/// Every folded literal carries the combined span of the expression it replaces, so diagnostics
+/// and the printer keep pointing at the original source.
+pub struct ConstantFolder {}
+
+impl ConstantFolder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn fold(&mut self, ast: &mut Ast) {
+        ast.visit(self);
+    }
+
+    pub(crate) fn constant_value(ast: &Ast, expr_id: ExprId) -> Option<Value> {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Number(number) => Some(Value::Number(number.number)),
+            ExprKind::Boolean(boolean) => Some(Value::Boolean(boolean.value)),
+            ExprKind::Parenthesized(expr) => Self::constant_value(ast, expr.expression),
+            _ => None,
+        }
+    }
+
+    /// Evaluates `expr_id` if it is built only from literals and operators that fold without
+    /// overflowing or dividing by zero.
+    pub(crate) fn evaluate_constant(ast: &Ast, expr_id: ExprId) -> Option<Value> {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Number(number) => Some(Value::Number(number.number)),
+            ExprKind::Boolean(boolean) => Some(Value::Boolean(boolean.value)),
+            ExprKind::Parenthesized(expr) => Self::evaluate_constant(ast, expr.expression),
+            ExprKind::Unary(unary) => {
+                let operand = Self::evaluate_constant(ast, unary.operand)?;
+                Self::fold_unary(&unary.operator.kind, operand)
+            }
+            ExprKind::Binary(binary) => {
+                let left = Self::evaluate_constant(ast, binary.left)?;
+                let right = Self::evaluate_constant(ast, binary.right)?;
+                Self::fold_binary(&binary.operator.kind, left, right)
+            }
+            _ => None,
+        }
+    }
+
+    fn fold_unary(operator: &UnOpKid, operand: Value) -> Option<Value> {
+        let operand = match operand {
+            Value::Number(operand) => operand,
+            _ => return None,
+        };
+        match operator {
+            UnOpKid::Minus => operand.checked_neg().map(Value::Number),
+            UnOpKid::BitwiseNot => Some(Value::Number(!operand)),
+        }
+    }
+
+    pub(crate) fn fold_binary(operator: &BinOpKind, left: Value, right: Value) -> Option<Value> {
+        let (left, right) = match (left, right) {
+            (Value::Number(left), Value::Number(right)) => (left, right),
+            _ => return None,
+        };
+        // overflowing operations and division by zero are left for the evaluator to report
+        match operator {
+            BinOpKind::Plus => left.checked_add(right).map(Value::Number),
+            BinOpKind::Minus => left.checked_sub(right).map(Value::Number),
+            BinOpKind::Multiply => left.checked_mul(right).map(Value::Number),
+            BinOpKind::Divide => left.checked_div(right).map(Value::Number),
+            BinOpKind::Power => u32::try_from(right).ok()
+                .and_then(|right| left.checked_pow(right))
+                .map(Value::Number),
+            BinOpKind::BitwiseAnd => Some(Value::Number(left & right)),
+            BinOpKind::BitwiseOr => Some(Value::Number(left | right)),
+            BinOpKind::BitwiseXor => Some(Value::Number(left ^ right)),
+            BinOpKind::Equals => Some(Value::Boolean(left == right)),
+            BinOpKind::NotEquals => Some(Value::Boolean(left != right)),
+            BinOpKind::LessThan => Some(Value::Boolean(left < right)),
+            BinOpKind::LessThanOrEqual => Some(Value::Boolean(left <= right)),
+            BinOpKind::GreaterThan => Some(Value::Boolean(left > right)),
+            BinOpKind::GreaterThanOrEqual => Some(Value::Boolean(left >= right)),
+        }
+    }
+
+    pub(crate) fn replace_with_constant(ast: &mut Ast, expr_id: ExprId, value: Value) {
+        // the token keeps the position of the folded expression, but its literal is the value
+        let span = ast.query_expr(expr_id).span(ast);
+        let token_span = |literal: String| lexer::TextSpan::new(span.start, span.end, literal);
+        let kind = match value {
+            Value::Number(number) => ExprKind::Number(NumberExpr {
+                number,
+                base: NumberBase::Decimal,
+                token: Token::new(TokenKind::Number(number), token_span(number.to_string())),
+            }),
+            Value::Boolean(value) => {
+                let token_kind = if value { TokenKind::True } else { TokenKind::False };
+                ExprKind::Boolean(BoolExpr {
+                    value,
+                    token: Token::new(token_kind, token_span(value.to_string())),
+                })
+            }
+            Value::Function(_) => unreachable!("Functions are never constant"),
+        };
+        ast.set_kind(expr_id, kind);
+    }
+}
+
+impl ASTVisitor for ConstantFolder {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+        let folded = Self::constant_value(ast, unary_expression.operand)
+            .and_then(|operand| Self::fold_unary(&unary_expression.operator.kind, operand));
+        if let Some(value) = folded {
+            Self::replace_with_constant(ast, expr.id, value);
+        }
+    }
+
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expression: &BinaryExpr, expr: &Expr) {
+        self.visit_expression(ast, binary_expression.left);
+        self.visit_expression(ast, binary_expression.right);
+        let left = Self::constant_value(ast, binary_expression.left);
+        let right = Self::constant_value(ast, binary_expression.right);
+        let folded = match (left, right) {
+            (Some(left), Some(right)) => Self::fold_binary(&binary_expression.operator.kind, left, right),
+            _ => None,
+        };
+        if let Some(value) = folded {
+            Self::replace_with_constant(ast, expr.id, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::{Ast, ExprId, ExprKind, StmtKind};
+    use crate::compilation_unit::CompilationUnit;
+    use crate::passes::constant_folder::ConstantFolder;
+
+    fn first_let_initializer(ast: &Ast) -> ExprId {
+        ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) => Some(let_stmt.initializer),
+            _ => None,
+        }).expect("Expected a let statement")
+    }
+
+    fn assert_folds_to_number(input: &str, expected: i64) {
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let ast = &mut compilation_unit.ast;
+        let initializer = first_let_initializer(ast);
+        let original_span = ast.query_expr(initializer).span(ast);
+
+        ConstantFolder::new().fold(ast);
+
+        let folded = ast.query_expr(initializer);
+        match &folded.kind {
+            ExprKind::Number(number) => {
+                assert_eq!(number.number, expected);
+                assert_eq!(number.literal(), expected.to_string());
+            }
+            kind => panic!("Expected folded number, found {:?}", kind),
+        }
+        let folded_span = folded.span(ast);
+        assert_eq!((folded_span.start, folded_span.end), (original_span.start, original_span.end));
+    }
+
+    #[test]
+    pub fn should_keep_combined_span_of_folded_binary_expression() {
+        assert_folds_to_number("let a = 2 + 3", 5);
+    }
+
+    #[test]
+    pub fn should_keep_combined_span_of_nested_folded_expressions() {
+        assert_folds_to_number("let a = -1 + 2 * 3", 5);
+    }
+
+    #[test]
+    pub fn should_fold_bitwise_not() {
+        assert_folds_to_number("let a = ~0", -1);
+    }
+
+    #[test]
+    pub fn should_fold_bitwise_not_of_folded_operand() {
+        assert_folds_to_number("let a = ~-6", 5);
+    }
+
+    #[test]
+    pub fn should_classify_expression_over_literals_as_constant() {
+        let compilation_unit = CompilationUnit::compile("let a = -2 + 3 * 4").expect("Failed to compile");
+        let ast = &compilation_unit.ast;
+
+        assert!(ast.is_constant(first_let_initializer(ast)));
+    }
+
+    #[test]
+    pub fn should_not_classify_expression_with_variable_as_constant() {
+        let input = "\
+        let x = 1
+        let a = x + 3
+        ";
+        let compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let ast = &compilation_unit.ast;
+        let initializer = ast.statements.iter().filter_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) => Some(let_stmt.initializer),
+            _ => None,
+        }).last().expect("Expected a let statement");
+
+        assert!(!ast.is_constant(initializer));
+    }
+
+    #[test]
+    pub fn should_not_classify_division_by_zero_as_constant() {
+        let compilation_unit = CompilationUnit::compile("let a = 1 / 0").expect("Failed to compile");
+        let ast = &compilation_unit.ast;
+
+        assert!(!ast.is_constant(first_let_initializer(ast)));
+    }
+}
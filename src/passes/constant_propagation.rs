@@ -0,0 +1,126 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::evaluator::Value;
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BinOpKind, BinaryExpr, BoolExpr, Expr, ExprId, ExprKind, FuncExpr, LetStmt, NumberExpr, RecExpr, Stmt, UnaryExpr, VarExpr};
+use crate::compilation_unit::VariableIdx;
+use crate::diagnostics::DiagnosticsBagCell;
+use crate::passes::constant_folder::ConstantFolder;
+use crate::text::span::TextSpan;
+
+/// Replaces uses of variables that are bound to a constant and never reassigned with that constant.
+///
+/// Divisions whose divisor is, or propagates to, zero are reported along the way. Runs on a
+/// resolved AST, since variables are tracked by their [`VariableIdx`].
+pub struct ConstantPropagator {
+    diagnostics: DiagnosticsBagCell,
+    constants: HashMap<VariableIdx, Value>,
+    reassigned: HashSet<VariableIdx>,
+}
+
+impl ConstantPropagator {
+    pub fn new(diagnostics: DiagnosticsBagCell) -> Self {
+        Self { diagnostics, constants: HashMap::new(), reassigned: HashSet::new() }
+    }
+
+    pub fn propagate(&mut self, ast: &mut Ast) {
+        self.reassigned = ast.expressions.iter().filter_map(|expr| match &expr.kind {
+            ExprKind::Assignment(assignment) => Some(assignment.variable_idx),
+            _ => None,
+        }).collect();
+        ast.visit(self);
+    }
+}
+
+impl ASTVisitor for ConstantPropagator {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+        if self.reassigned.contains(&let_statement.variable_idx) {
+            return;
+        }
+        if let Some(value) = ConstantFolder::constant_value(ast, let_statement.initializer) {
+            self.constants.insert(let_statement.variable_idx, value);
+        }
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {
+        if let Some(value) = self.constants.get(&variable_expression.variable_idx) {
+            ConstantFolder::replace_with_constant(ast, expr.id, value.clone());
+        }
+    }
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expression: &BinaryExpr, expr: &Expr) {
+        // taken before the operands are replaced, so the diagnostic shows the source text
+        let span = expr.span(ast);
+        self.visit_expression(ast, binary_expression.left);
+        self.visit_expression(ast, binary_expression.right);
+        if let BinOpKind::Divide = binary_expression.operator.kind {
+            if let Some(Value::Number(0)) = ConstantFolder::constant_value(ast, binary_expression.right) {
+                self.diagnostics.borrow_mut().report_division_by_zero(&span);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::compilation_unit::CompilationUnit;
+    use crate::diagnostics::{DiagnosticsBag, DiagnosticsBagCell};
+    use crate::passes::constant_propagation::ConstantPropagator;
+
+    fn propagate(input: &str) -> Vec<(String, String)> {
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        ConstantPropagator::new(Rc::clone(&diagnostics)).propagate(&mut compilation_unit.ast);
+        let diagnostics = diagnostics.borrow();
+        diagnostics.diagnostics.iter()
+            .map(|diagnostic| (diagnostic.message.clone(), diagnostic.span.literal.clone()))
+            .collect()
+    }
+
+    #[test]
+    pub fn should_report_division_by_propagated_zero() {
+        let input = "\
+        let x = 10
+        let z = 0
+        let a = x / z
+        ";
+
+        assert_eq!(propagate(input), vec![("Division by zero".to_string(), "x / z".to_string())]);
+    }
+
+    #[test]
+    pub fn should_not_report_division_by_non_constant_divisor() {
+        let input = "\
+        let z = 0
+        z = 1
+        let a = 10 / z
+        let f = func (d: int) -> int {
+            return 10 / d
+        }
+        ";
+
+        assert!(propagate(input).is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BoolExpr, BreakStmt, ContinueStmt, Expr, ExprId, FuncExpr, LetStmt, NumberExpr, RecExpr, ReturnStmt, Stmt, UnaryExpr, VarExpr, WhileStmt};
+use crate::diagnostics::DiagnosticsBagCell;
+use crate::text::span::TextSpan;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+    Function,
+    Loop,
+}
+
+/// Reports `break` and `continue` outside of a loop and `return` outside of a function.
+///
+/// A function body starts a fresh context, so a loop surrounding a function does not make
+/// `break` inside that function valid.
+pub struct ControlFlowValidator {
+    diagnostics: DiagnosticsBagCell,
+    contexts: Vec<Context>,
+}
+
+impl ControlFlowValidator {
+    pub fn new(diagnostics: DiagnosticsBagCell) -> Self {
+        Self { diagnostics, contexts: Vec::new() }
+    }
+
+    pub fn validate(&mut self, ast: &mut Ast) {
+        ast.visit(self);
+    }
+
+    fn is_inside_loop(&self) -> bool {
+        self.contexts.iter().rev()
+            .take_while(|context| **context != Context::Function)
+            .any(|context| *context == Context::Loop)
+    }
+
+    fn is_inside_function(&self) -> bool {
+        self.contexts.contains(&Context::Function)
+    }
+}
+
+impl ASTVisitor for ControlFlowValidator {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.contexts.push(Context::Function);
+            self.visit_expression(ast, body);
+            self.contexts.pop();
+        }
+    }
+
+    fn visit_return_statement(&mut self, ast: &mut Ast, return_statement: &ReturnStmt) {
+        if !self.is_inside_function() {
+            self.diagnostics.borrow_mut().report_cannot_return_outside_function(&return_statement.return_keyword);
+        }
+        if let Some(return_value) = &return_statement.return_value {
+            self.visit_expression(ast, *return_value);
+        }
+    }
+
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
+        self.visit_expression(ast, while_statement.condition);
+        self.contexts.push(Context::Loop);
+        self.visit_expression(ast, while_statement.body);
+        self.contexts.pop();
+    }
+
+    fn visit_break_statement(&mut self, ast: &mut Ast, break_statement: &BreakStmt) {
+        if !self.is_inside_loop() {
+            self.diagnostics.borrow_mut().report_cannot_use_break_outside_of_loop(&break_statement.break_keyword);
+        }
+    }
+
+    fn visit_continue_statement(&mut self, ast: &mut Ast, continue_statement: &ContinueStmt) {
+        if !self.is_inside_loop() {
+            self.diagnostics.borrow_mut().report_cannot_use_continue_outside_of_loop(&continue_statement.continue_keyword);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BoolExpr, Expr, ExprId, ExprKind, FuncExpr, LetStmt, NumberExpr, RecExpr, Stmt, UnaryExpr, VarExpr};
+use crate::text::span::TextSpan;
+
+/// Removes pairs of identical unary operators, so `--x` and `~~x` become `x`.
+///
+/// The collapsed expression takes over the operand's node, including its span. Different
+/// operators, as in `-~x`, are left alone.
+pub struct DoubleNegationEliminator {}
+
+impl DoubleNegationEliminator {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn eliminate(&mut self, ast: &mut Ast) {
+        ast.visit(self);
+    }
+}
+
+impl ASTVisitor for DoubleNegationEliminator {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+        let inner_operand = match &ast.query_expr(unary_expression.operand).kind {
+            ExprKind::Unary(inner) if inner.operator.kind == unary_expression.operator.kind => inner.operand,
+            _ => return,
+        };
+        let kind = ast.query_expr(inner_operand).kind.clone();
+        ast.set_kind(expr.id, kind);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::printer::test::assert_ast_eq;
+    use crate::ast::StmtKind;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::passes::double_negation::DoubleNegationEliminator;
+
+    fn assert_eliminates_to(input: &str, expected: &str) {
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        DoubleNegationEliminator::new().eliminate(&mut compilation_unit.ast);
+        assert_ast_eq(expected, &mut compilation_unit.ast);
+    }
+
+    #[test]
+    pub fn should_collapse_double_minus_keeping_operand_span() {
+        let input = "let x = 1\nlet a = --x";
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+
+        DoubleNegationEliminator::new().eliminate(&mut compilation_unit.ast);
+
+        let ast = &compilation_unit.ast;
+        let initializer = ast.statements.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Let(let_stmt) if let_stmt.identifier.span.literal == "a" => Some(let_stmt.initializer),
+            _ => None,
+        }).expect("Expected a declaration of a");
+        let span = ast.query_expr(initializer).span(ast);
+        assert_eq!((span.start, span.literal.as_str()), (input.rfind('x').unwrap(), "x"));
+    }
+
+    #[test]
+    pub fn should_collapse_double_bitwise_not() {
+        assert_eliminates_to("let x = 1\nlet a = ~~x", "let x = 1\nlet a = x");
+    }
+
+    #[test]
+    pub fn should_keep_one_of_three_negations() {
+        assert_eliminates_to("let x = 1\nlet a = ---x", "let x = 1\nlet a = -x");
+    }
+
+    #[test]
+    pub fn should_not_collapse_different_operators() {
+        assert_eliminates_to("let x = 1\nlet a = -~x", "let x = 1\nlet a = -~x");
+    }
+}
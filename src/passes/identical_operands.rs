@@ -0,0 +1,159 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BinOpKind, BinaryExpr, BoolExpr, Expr, ExprId, ExprKind, FuncExpr, LetStmt, NumberExpr, RecExpr, Stmt, UnaryExpr, VarExpr};
+use crate::diagnostics::DiagnosticsBagCell;
+use crate::text::span::TextSpan;
+
+/// Warns about binary expressions whose operands are the same pure expression, such as `x - x`.
+///
+/// Such an expression has a constant or trivial value, which is suggested as a replacement.
+/// Operands are compared structurally and only when they are built from variables, literals and
+/// operators, since a call or an assignment may give a different value each time. Variables are
+/// compared by the binding they resolve to, not by name.
+pub struct IdenticalOperandsLint {
+    diagnostics: DiagnosticsBagCell,
+}
+
+impl IdenticalOperandsLint {
+    pub fn new(diagnostics: DiagnosticsBagCell) -> Self {
+        Self { diagnostics }
+    }
+
+    pub fn lint(&mut self, ast: &mut Ast) {
+        ast.visit(self);
+    }
+
+    fn simplification(ast: &Ast, binary_expression: &BinaryExpr) -> Option<String> {
+        let simplification = match binary_expression.operator.kind {
+            BinOpKind::Minus | BinOpKind::BitwiseXor => "0",
+            BinOpKind::Equals | BinOpKind::LessThanOrEqual | BinOpKind::GreaterThanOrEqual => "true",
+            BinOpKind::NotEquals | BinOpKind::LessThan | BinOpKind::GreaterThan => "false",
+            BinOpKind::BitwiseAnd | BinOpKind::BitwiseOr => {
+                return Some(ast.query_expr(binary_expression.left).span(ast).literal);
+            }
+            // `x / x` is not `1` when `x` is zero
+            BinOpKind::Plus | BinOpKind::Multiply | BinOpKind::Power | BinOpKind::Divide => return None,
+        };
+        Some(simplification.to_string())
+    }
+
+    fn is_same_pure_expression(ast: &Ast, left: ExprId, right: ExprId) -> bool {
+        match (&ast.query_expr(left).kind, &ast.query_expr(right).kind) {
+            (ExprKind::Parenthesized(left), _) => Self::is_same_pure_expression(ast, left.expression, right),
+            (_, ExprKind::Parenthesized(right)) => Self::is_same_pure_expression(ast, left, right.expression),
+            (ExprKind::Variable(left), ExprKind::Variable(right)) => left.variable_idx == right.variable_idx,
+            (ExprKind::Number(left), ExprKind::Number(right)) => left.number == right.number,
+            (ExprKind::Boolean(left), ExprKind::Boolean(right)) => left.value == right.value,
+            (ExprKind::Unary(left), ExprKind::Unary(right)) => {
+                left.operator.kind == right.operator.kind
+                    && Self::is_same_pure_expression(ast, left.operand, right.operand)
+            }
+            (ExprKind::Binary(left), ExprKind::Binary(right)) => {
+                left.operator.kind == right.operator.kind
+                    && Self::is_same_pure_expression(ast, left.left, right.left)
+                    && Self::is_same_pure_expression(ast, left.right, right.right)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ASTVisitor for IdenticalOperandsLint {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_binary_expression(&mut self, ast: &mut Ast, binary_expression: &BinaryExpr, expr: &Expr) {
+        self.visit_expression(ast, binary_expression.left);
+        self.visit_expression(ast, binary_expression.right);
+        if !Self::is_same_pure_expression(ast, binary_expression.left, binary_expression.right) {
+            return;
+        }
+        if let Some(simplification) = Self::simplification(ast, binary_expression) {
+            self.diagnostics.borrow_mut().report_identical_operands(&expr.span(ast), &binary_expression.operator.token, &simplification);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::passes::identical_operands::IdenticalOperandsLint;
+    use crate::passes::test::run_lint;
+
+    fn lint(input: &str) -> Vec<String> {
+        let diagnostics = run_lint(input, |diagnostics, ast| IdenticalOperandsLint::new(diagnostics).lint(ast));
+        diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect()
+    }
+
+    #[test]
+    pub fn should_warn_about_subtracting_variable_from_itself() {
+        let input = "\
+        let x = 1
+        let a = x - x
+        ";
+
+        assert_eq!(lint(input), vec!["Both operands of '-' are identical, consider replacing with '0'"]);
+    }
+
+    #[test]
+    pub fn should_suggest_true_for_comparing_expression_with_itself() {
+        let input = "\
+        let x = 1
+        let a = (x + 1) == (x + 1)
+        ";
+
+        assert_eq!(lint(input), vec!["Both operands of '==' are identical, consider replacing with 'true'"]);
+    }
+
+    #[test]
+    pub fn should_warn_about_subtracting_shadowing_parameter_from_itself() {
+        let input = "\
+        let x = true
+        let f = func (x: int) -> int {
+            return x - x
+        }
+        ";
+
+        assert_eq!(lint(input), vec!["Both operands of '-' are identical, consider replacing with '0'"]);
+    }
+
+    #[test]
+    pub fn should_not_suggest_one_for_dividing_variable_by_itself() {
+        let input = "\
+        let x = 0
+        let a = x / x
+        ";
+
+        assert!(lint(input).is_empty());
+    }
+
+    #[test]
+    pub fn should_not_warn_about_different_operands() {
+        let input = "\
+        let x = 1
+        let y = 2
+        let a = x - y
+        ";
+
+        assert!(lint(input).is_empty());
+    }
+}
@@ -0,0 +1,31 @@
+pub mod annotation_collector;
+pub mod block_flattener;
+pub mod cfg_builder;
+pub mod commutative_canonicalizer;
+pub mod constant_folder;
+pub mod constant_propagation;
+pub mod control_flow_validator;
+pub mod double_negation;
+pub mod identical_operands;
+pub mod no_effect;
+pub mod unused_parameters;
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::ast::Ast;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::diagnostics::{Diagnostic, DiagnosticsBag, DiagnosticsBagCell};
+
+    /// Compiles `input` and runs `lint` over it with an empty diagnostics bag, returning what
+    /// the lint reported.
+    pub fn run_lint(input: &str, lint: impl FnOnce(DiagnosticsBagCell, &mut Ast)) -> Vec<Diagnostic> {
+        let mut compilation_unit = CompilationUnit::compile(input).expect("Failed to compile");
+        let diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticsBag::new()));
+        lint(Rc::clone(&diagnostics), &mut compilation_unit.ast);
+        let diagnostics = diagnostics.borrow();
+        diagnostics.diagnostics.clone()
+    }
+}
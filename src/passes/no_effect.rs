@@ -0,0 +1,163 @@
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BlockExpr, BoolExpr, Expr, ExprId, ExprKind, FuncExpr, ItemKind, LetStmt, NumberExpr, RecExpr, Stmt, StmtId, StmtKind, UnaryExpr, VarExpr, WhileStmt};
+use crate::diagnostics::DiagnosticsBagCell;
+use crate::text::span::TextSpan;
+use crate::typings::Type;
+
+/// Warns about expression statements that compute a value without any effect, such as `1 + 2`.
+///
+/// An expression is pure when it is built from variables, literals and operators only, so calls
+/// and assignments are never reported. The last statement of a block is its value and the last
+/// top-level statement is the program's result, so neither is discarded, except in a `while` body,
+/// whose value is always discarded.
+pub struct NoEffectLint {
+    diagnostics: DiagnosticsBagCell,
+}
+
+impl NoEffectLint {
+    pub fn new(diagnostics: DiagnosticsBagCell) -> Self {
+        Self { diagnostics }
+    }
+
+    pub fn lint(&mut self, ast: &mut Ast) {
+        let statements: Vec<StmtId> = ast.items.iter().map(|item| match &item.kind {
+            ItemKind::Stmt(stmt_id) => *stmt_id,
+        }).collect();
+        self.lint_statements(ast, &statements, true);
+    }
+
+    /// `keeps_last` tells whether the value of the last statement is used.
+    fn lint_statements(&mut self, ast: &mut Ast, statements: &[StmtId], keeps_last: bool) {
+        let discarded = match statements.split_last() {
+            Some((_, discarded)) if keeps_last => discarded,
+            _ => statements,
+        };
+        for stmt_id in discarded {
+            self.check_discarded_statement(ast, *stmt_id);
+        }
+        for stmt_id in statements {
+            self.visit_statement(ast, *stmt_id);
+        }
+    }
+
+    fn check_discarded_statement(&mut self, ast: &Ast, stmt_id: StmtId) {
+        let expr = match &ast.query_stmt(stmt_id).kind {
+            StmtKind::Expr(expr_id) => ast.query_expr(*expr_id),
+            _ => return,
+        };
+        if expr.ty != Type::Void && Self::is_pure(ast, expr.id) {
+            self.diagnostics.borrow_mut().report_expression_without_effect(&expr.span(ast));
+        }
+    }
+
+    fn is_pure(ast: &Ast, expr_id: ExprId) -> bool {
+        match &ast.query_expr(expr_id).kind {
+            ExprKind::Number(_) | ExprKind::Boolean(_) | ExprKind::Variable(_) => true,
+            ExprKind::Parenthesized(parenthesized) => Self::is_pure(ast, parenthesized.expression),
+            ExprKind::Unary(unary) => Self::is_pure(ast, unary.operand),
+            ExprKind::Binary(binary) => Self::is_pure(ast, binary.left) && Self::is_pure(ast, binary.right),
+            _ => false,
+        }
+    }
+}
+
+impl ASTVisitor for NoEffectLint {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_block_expr(&mut self, ast: &mut Ast, block_expr: &BlockExpr, expr: &Expr) {
+        self.lint_statements(ast, &block_expr.stmts, true);
+    }
+
+    fn visit_while_statement(&mut self, ast: &mut Ast, while_statement: &WhileStmt) {
+        self.visit_expression(ast, while_statement.condition);
+        let body = ast.query_expr(while_statement.body);
+        match &body.kind {
+            ExprKind::Block(block) => {
+                let statements = block.stmts.clone();
+                self.lint_statements(ast, &statements, false);
+            }
+            _ => self.visit_expression(ast, while_statement.body),
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::passes::no_effect::NoEffectLint;
+    use crate::passes::test::run_lint;
+
+    fn lint(input: &str) -> Vec<String> {
+        let diagnostics = run_lint(input, |diagnostics, ast| NoEffectLint::new(diagnostics).lint(ast));
+        diagnostics.iter().map(|diagnostic| diagnostic.span.literal.clone()).collect()
+    }
+
+    #[test]
+    pub fn should_flag_discarded_pure_expressions() {
+        let input = "\
+        let x = 1
+        1 + 2
+        let a = {
+            x < 2
+            x
+        }
+        a
+        ";
+
+        assert_eq!(lint(input), vec!["1 + 2", "x < 2"]);
+    }
+
+    #[test]
+    pub fn should_flag_last_statement_of_while_body() {
+        let input = "\
+        let c = true
+        while c { 1 + 2 }
+        ";
+
+        assert_eq!(lint(input), vec!["1 + 2"]);
+    }
+
+    #[test]
+    pub fn should_not_flag_calls_and_assignments() {
+        let input = "\
+        extern func f() -> int
+        let x = 1
+        f()
+        x = 2
+        x
+        ";
+
+        assert!(lint(input).is_empty());
+    }
+
+    #[test]
+    pub fn should_not_flag_discarded_expression() {
+        let input = "\
+        _ = 1 + 2
+        let a = 1
+        ";
+
+        assert!(lint(input).is_empty());
+    }
+}
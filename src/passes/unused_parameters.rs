@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use crate::ast::def_use::{def_use_chains, DefUse};
+use crate::ast::visitor::ASTVisitor;
+use crate::ast::{Ast, BoolExpr, Expr, ExprId, FuncExpr, LetStmt, NumberExpr, RecExpr, Stmt, UnaryExpr, VarExpr};
+use crate::compilation_unit::VariableIdx;
+use crate::diagnostics::DiagnosticsBagCell;
+use crate::text::span::TextSpan;
+
+/// Prefix of parameter names that are unused on purpose.
+const UNUSED_PREFIX: char = '_';
+
+/// Warns about function parameters that are never read in the function body.
+///
+/// Parameters whose name starts with `_` are not reported. Extern declarations have no body and
+/// are skipped.
+pub struct UnusedParameterLint {
+    diagnostics: DiagnosticsBagCell,
+    chains: HashMap<VariableIdx, DefUse>,
+}
+
+impl UnusedParameterLint {
+    pub fn new(diagnostics: DiagnosticsBagCell) -> Self {
+        Self { diagnostics, chains: HashMap::new() }
+    }
+
+    pub fn lint(&mut self, ast: &mut Ast) {
+        self.chains = def_use_chains(ast);
+        ast.visit(self);
+    }
+}
+
+impl ASTVisitor for UnusedParameterLint {
+    fn visit_func_expr(&mut self, ast: &mut Ast, func_expr: &FuncExpr, expr_id: ExprId) {
+        for parameter in &func_expr.decl.parameters {
+            let is_used = parameter.variable_idx.and_then(|variable_idx| self.chains.get(&variable_idx)).map_or(false, |chain| !chain.uses.is_empty());
+            if !is_used && !parameter.identifier.span.literal.starts_with(UNUSED_PREFIX) {
+                self.diagnostics.borrow_mut().report_unused_parameter(&parameter.identifier);
+            }
+        }
+        if let Some(body) = func_expr.decl.body {
+            self.visit_expression(ast, body);
+        }
+    }
+
+    fn visit_let_statement(&mut self, ast: &mut Ast, let_statement: &LetStmt, stmt: &Stmt) {
+        self.visit_expression(ast, let_statement.initializer);
+    }
+
+    fn visit_rec_expression(&mut self, ast: &mut Ast, expr: &RecExpr, expr_id: ExprId) {}
+
+    fn visit_variable_expression(&mut self, ast: &mut Ast, variable_expression: &VarExpr, expr: &Expr) {}
+
+    fn visit_number_expression(&mut self, ast: &mut Ast, number: &NumberExpr, expr: &Expr) {}
+
+    fn visit_boolean_expression(&mut self, ast: &mut Ast, boolean: &BoolExpr, expr: &Expr) {}
+
+    fn visit_error(&mut self, ast: &mut Ast, span: &TextSpan) {}
+
+    fn visit_unary_expression(&mut self, ast: &mut Ast, unary_expression: &UnaryExpr, expr: &Expr) {
+        self.visit_expression(ast, unary_expression.operand);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::passes::unused_parameters::UnusedParameterLint;
+    use crate::passes::test::run_lint;
+
+    fn lint(input: &str) -> Vec<String> {
+        let diagnostics = run_lint(input, |diagnostics, ast| UnusedParameterLint::new(diagnostics).lint(ast));
+        diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect()
+    }
+
+    #[test]
+    pub fn should_warn_about_unused_parameter() {
+        let input = "let f = func (a: int, b: int) -> int { return a }";
+
+        assert_eq!(lint(input), vec!["Parameter 'b' is never used, prefix it with '_' if this is intended"]);
+    }
+
+    #[test]
+    pub fn should_not_warn_about_underscore_prefixed_parameter() {
+        let input = "let f = func (a: int, _b: int) -> int { return a }";
+
+        assert!(lint(input).is_empty());
+    }
+}
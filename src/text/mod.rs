@@ -8,10 +8,12 @@ impl SourceText {
         Self { text }
     }
     pub fn line_index(&self, position: usize) -> usize {
-        self.text[..=position].lines().count() - 1
+        let position = position.min(self.text.len());
+        self.text[..position].matches('\n').count()
     }
+    /// Returns the line at `index`, or an empty line past the end of the text.
     pub fn get_line(&self, index: usize) -> &str {
-        self.text.lines().nth(index).unwrap()
+        self.text.lines().nth(index).unwrap_or("")
     }
     pub fn line_start(&self, index: usize) -> usize {
         self.text
@@ -1,9 +1,13 @@
+use crate::ast::lexer::Token;
+use crate::ast::StaticTypeAnnotation;
 use crate::compilation_unit::FunctionIdx;
+use crate::text::span::TextSpan;
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Int,
+    Float,
     Bool,
     Void,
     Function(FunctionIdx),
@@ -15,6 +19,7 @@ impl Display for Type {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let type_name = match self {
             Type::Int => "int",
+            Type::Float => "float",
             Type::Bool => "bool",
             Type::Unresolved => "unresolved",
             Type::Void => "void",
@@ -25,22 +30,185 @@ impl Display for Type {
     }
 }
 
+/// How lenient the type checker is about assigning a value of one type to another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeCheckStrictness {
+    /// Implicit widening, such as `int` to `float`, is allowed.
+    Lenient,
+    /// Only exact matches are allowed, plus `Error` so that one type error is not reported twice.
+    Strict,
+}
+
+impl Default for TypeCheckStrictness {
+    fn default() -> Self {
+        TypeCheckStrictness::Lenient
+    }
+}
+
+/// A type annotation naming a type that does not exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    /// The type name of the annotation.
+    pub span: TextSpan,
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Undeclared type '{}'", self.span.literal)
+    }
+}
+
 impl Type {
     pub fn is_assignable_to(&self, other: &Type) -> bool {
+        self.is_assignable_with_strictness(other, TypeCheckStrictness::Lenient)
+    }
+    /// Like [`Type::is_assignable_to`], without implicit widening when `strictness` is `Strict`.
+    pub fn is_assignable_with_strictness(&self, other: &Type, strictness: TypeCheckStrictness) -> bool {
         match (self, other) {
             (Type::Int, Type::Int) => true,
+            (Type::Float, Type::Float) => true,
+            (from, to) if strictness == TypeCheckStrictness::Lenient && Type::widen(from, to) => true,
             (Type::Bool, Type::Bool) => true,
             (Type::Error, _) => true,
             (_, Type::Error) => true,
             _ => false,
         }
     }
+    /// Whether a value of type `from` converts to `to` without losing its meaning, which
+    /// is the only conversion applied implicitly.
+    pub fn widen(from: &Type, to: &Type) -> bool {
+        matches!((from, to), (Type::Int, Type::Float))
+    }
+    /// Whether a value of type `from` may be converted to `to` by an explicit cast, possibly
+    /// losing precision or range.
+    pub fn narrow(from: &Type, to: &Type) -> bool {
+        matches!((from, to), (Type::Float, Type::Int))
+    }
+    /// Whether values of this type are copied on assignment and when passed as an argument.
+    ///
+    /// Scalars are copied. A function value only refers to its declaration, so copying it
+    /// shares the function. Aggregate and heap-allocated types, once the language has them,
+    /// must be shared by reference instead and return `false`. `Error` and `Unresolved` count as copy
+    /// so that a type error is not followed by spurious ownership errors. Every variant is listed on
+    /// purpose: a new type has to decide here.
+    pub fn is_copy(&self) -> bool {
+        match self {
+            Type::Int => true,
+            Type::Float => true,
+            Type::Bool => true,
+            Type::Void => true,
+            Type::Function(_) => true,
+            Type::Unresolved => true,
+            Type::Error => true,
+        }
+    }
+    /// Resolves the type named by `annotation`.
+    pub fn from_annotation(annotation: &StaticTypeAnnotation) -> Result<Type, TypeError> {
+        Self::from_type_name(&annotation.type_name)
+    }
+    /// Resolves the type named by the `type_name` token of an annotation or a return type.
+    pub fn from_type_name(type_name: &Token) -> Result<Type, TypeError> {
+        let span = &type_name.span;
+        Self::from_str(&span.literal).ok_or_else(|| TypeError { span: TextSpan::new(span.start, span.end, span.literal.clone()) })
+    }
     pub fn from_str(s: &str) -> Option<Type> {
         match s {
             "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
             "bool" => Some(Type::Bool),
             "void" => Some(Type::Void),
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use rs_compiler::Idx;
+
+    use crate::ast::lexer::{self, Token, TokenKind};
+    use crate::ast::StaticTypeAnnotation;
+    use crate::compilation_unit::{compile, FunctionIdx};
+    use crate::text::span::TextSpan;
+    use crate::typings::{Type, TypeError};
+
+    fn annotation(type_name: &str) -> StaticTypeAnnotation {
+        StaticTypeAnnotation {
+            colon: Token::new(TokenKind::Colon, lexer::TextSpan::new(5, 6, ":".to_string())),
+            type_name: Token::new(TokenKind::Identifier, lexer::TextSpan::new(7, 7 + type_name.len(), type_name.to_string())),
+        }
+    }
+
+    #[test]
+    pub fn should_classify_scalar_and_function_types_as_copy() {
+        assert!(Type::Int.is_copy());
+        assert!(Type::Float.is_copy());
+        assert!(Type::Bool.is_copy());
+        assert!(Type::Void.is_copy());
+        assert!(Type::Function(FunctionIdx::new(0)).is_copy());
+        assert!(Type::Unresolved.is_copy());
+        assert!(Type::Error.is_copy());
+    }
+
+    #[test]
+    pub fn should_widen_int_to_float() {
+        assert!(Type::Int.is_assignable_to(&Type::Float));
+        assert!(Type::Float.is_assignable_to(&Type::Float));
+    }
+
+    #[test]
+    pub fn should_not_narrow_float_to_int() {
+        assert!(!Type::Float.is_assignable_to(&Type::Int));
+        assert!(!Type::Float.is_assignable_to(&Type::Bool));
+    }
+
+    #[test]
+    pub fn should_accept_error_on_either_side_of_float() {
+        assert!(Type::Error.is_assignable_to(&Type::Float));
+        assert!(Type::Float.is_assignable_to(&Type::Error));
+    }
+
+    #[test]
+    pub fn should_parse_float_type_name() {
+        assert_eq!(Type::from_str("float"), Some(Type::Float));
+        assert_eq!(Type::Float.to_string(), "float");
+    }
+
+    #[test]
+    pub fn should_widen_but_not_narrow_int_to_float() {
+        assert!(Type::widen(&Type::Int, &Type::Float));
+        assert!(!Type::narrow(&Type::Int, &Type::Float));
+    }
+
+    #[test]
+    pub fn should_require_narrowing_from_float_to_int() {
+        assert!(!Type::widen(&Type::Float, &Type::Int));
+        assert!(Type::narrow(&Type::Float, &Type::Int));
+        assert!(!Type::Float.is_assignable_to(&Type::Int));
+    }
+
+    #[test]
+    pub fn should_resolve_annotation() {
+        assert_eq!(Type::from_annotation(&annotation("int")), Ok(Type::Int));
+    }
+
+    #[test]
+    pub fn should_report_unknown_annotation_with_its_span() {
+        let error = Type::from_annotation(&annotation("foo"));
+
+        assert_eq!(error, Err(TypeError { span: TextSpan::new(7, 10, "foo".to_string()) }));
+        assert_eq!(error.unwrap_err().to_string(), "Undeclared type 'foo'");
+    }
+
+    #[test]
+    pub fn should_compile_int_assigned_to_float() {
+        assert!(compile("let x: float = 3").is_ok());
+    }
+
+    #[test]
+    pub fn should_not_compile_float_returned_as_int() {
+        let diagnostics = compile("let f = func (a: float) -> int { return a }").err().expect("Expected diagnostics");
+
+        assert_eq!(diagnostics[0].message, "Expected type 'int', found 'float'");
+    }
+}